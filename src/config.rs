@@ -1 +1,513 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
 pub const NUM_RECORDS: usize = 5_000;
+pub const DATASET_PATH: &str = "users.json";
+// Dataset files are read from here unless `DATA_DIR` names another directory
+pub const DATA_DIR: &str = ".";
+
+pub const MAX_RECORDS: usize = 10_000_000;
+pub const MAX_REPETITIONS: usize = 100;
+pub const MAX_WARMUP: usize = 100;
+pub const MAX_CONCURRENCY: usize = 1_024;
+// Open-loop bounds: slower rates would hold the benchmark lock for days
+pub const MIN_TARGET_RATE: f64 = 1.0;
+pub const MAX_PACED_PHASE_SECS: f64 = 3_600.0;
+// YCSB's default zipfian skew, and its default hotspot shape
+pub const ZIPFIAN_THETA: f64 = 0.99;
+pub const HOT_SET_FRACTION: f64 = 0.2;
+pub const HOT_OPERATION_FRACTION: f64 = 0.8;
+
+// Keeps a Postgres multi-row INSERT (9 binds per row) under 65535 parameters
+pub const MAX_BATCH_SIZE: usize = 7_000;
+// Extra bytes per record; well under MongoDB's 16 MiB document limit
+pub const MAX_PAYLOAD_BYTES: usize = 1 << 20;
+// Payload across the whole dataset, which is held in memory for the run
+pub const MAX_DATASET_PAYLOAD_BYTES: usize = 2 << 30;
+// Payload in one batch; well under PostgreSQL's 1 GB message limit
+pub const MAX_BATCH_PAYLOAD_BYTES: usize = 256 << 20;
+
+// Where benchmark records come from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DatasetSource {
+    // Read record by record; the format follows the extension unless given
+    File {
+        path: String,
+        #[serde(default)]
+        format: Option<FileFormat>,
+    },
+    // Seeded synthetic records, as many as `record_count` asks for
+    Generated(GeneratorParams),
+}
+
+/// Resolve a client-named dataset file against the data directory
+///
+/// Only plain relative paths are accepted, so a request cannot name a file
+/// outside `DATA_DIR` with an absolute path or `..`.
+pub fn dataset_path(path: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(
+            "dataset path must be relative to the data directory, without `..`".to_string(),
+        );
+    }
+    let base = std::env::var("DATA_DIR").unwrap_or_else(|_| DATA_DIR.to_string());
+    Ok(Path::new(&base).join(relative))
+}
+
+// Layout of a dataset file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    // One JSON array of records
+    Json,
+    // One JSON record per line
+    Ndjson,
+    // Header row naming the columns, then one record per row
+    Csv,
+}
+
+impl FileFormat {
+    /// Format implied by the file extension: `.ndjson`/`.jsonl`, `.csv`, else JSON
+    pub fn for_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("ndjson") | Some("jsonl") => FileFormat::Ndjson,
+            Some("csv") => FileFormat::Csv,
+            _ => FileFormat::Json,
+        }
+    }
+}
+
+// Shape of generated records
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneratorParams {
+    // Share of records with `active` set
+    pub active_ratio: f64,
+    // Ages are normal around `age_mean`, clamped to `min_age..=max_age`
+    pub age_mean: f64,
+    pub age_stddev: f64,
+    pub min_age: i32,
+    pub max_age: i32,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        Self {
+            active_ratio: 0.7,
+            age_mean: 40.0,
+            age_stddev: 14.0,
+            min_age: 18,
+            max_age: 90,
+        }
+    }
+}
+
+impl GeneratorParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.active_ratio) {
+            return Err("active_ratio must be between 0 and 1".to_string());
+        }
+        if !self.age_mean.is_finite() || !self.age_stddev.is_finite() || self.age_stddev < 0.0 {
+            return Err("age_mean must be finite and age_stddev non-negative".to_string());
+        }
+        if self.min_age > self.max_age {
+            return Err("min_age must not exceed max_age".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for DatasetSource {
+    fn default() -> Self {
+        DatasetSource::File {
+            path: DATASET_PATH.to_string(),
+            format: None,
+        }
+    }
+}
+
+// Extra fields added to every record, so engines are measured on larger documents
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PayloadParams {
+    // Approximate extra bytes per record, shared evenly by the enabled fields;
+    // every engine stores this much field content, plus its own framing
+    pub size: usize,
+    // Random text
+    pub padding: bool,
+    // Object with a bio and a nested address
+    pub nested: bool,
+    // Array of short strings
+    pub tags: bool,
+    // Random bytes
+    pub blob: bool,
+}
+
+impl Default for PayloadParams {
+    fn default() -> Self {
+        Self {
+            size: 1_024,
+            padding: true,
+            nested: false,
+            tags: false,
+            blob: false,
+        }
+    }
+}
+
+impl PayloadParams {
+    pub fn fields(&self) -> usize {
+        [self.padding, self.nested, self.tags, self.blob]
+            .iter()
+            .filter(|&&enabled| enabled)
+            .count()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.size > MAX_PAYLOAD_BYTES {
+            return Err(format!(
+                "payload size must be at most {}",
+                MAX_PAYLOAD_BYTES
+            ));
+        }
+        if self.fields() == 0 {
+            return Err("payload must enable at least one field".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn default_theta() -> f64 {
+    ZIPFIAN_THETA
+}
+
+fn default_hot_set_fraction() -> f64 {
+    HOT_SET_FRACTION
+}
+
+fn default_hot_operation_fraction() -> f64 {
+    HOT_OPERATION_FRACTION
+}
+
+// How point operations pick keys among the loaded records
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum KeyDistribution {
+    // Every record equally likely
+    Uniform,
+    // A few records far more popular than the rest; theta in (0, 1) sets the skew
+    Zipfian {
+        #[serde(default = "default_theta")]
+        theta: f64,
+    },
+    // `hot_operation_fraction` of operations go to the first
+    // `hot_set_fraction` of the records, the rest spread over the others
+    Hotspot {
+        #[serde(default = "default_hot_set_fraction")]
+        hot_set_fraction: f64,
+        #[serde(default = "default_hot_operation_fraction")]
+        hot_operation_fraction: f64,
+    },
+    // Zipfian over recency: the most recently loaded or inserted records win
+    Latest {
+        #[serde(default = "default_theta")]
+        theta: f64,
+    },
+}
+
+impl KeyDistribution {
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            KeyDistribution::Uniform => Ok(()),
+            KeyDistribution::Zipfian { theta } | KeyDistribution::Latest { theta } => {
+                if theta > 0.0 && theta < 1.0 {
+                    Ok(())
+                } else {
+                    Err("distribution theta must be between 0 and 1, exclusive".to_string())
+                }
+            }
+            KeyDistribution::Hotspot {
+                hot_set_fraction,
+                hot_operation_fraction,
+            } => {
+                if !(hot_set_fraction > 0.0 && hot_set_fraction <= 1.0) {
+                    Err("hot_set_fraction must be in (0, 1]".to_string())
+                } else if !(0.0..=1.0).contains(&hot_operation_fraction) {
+                    Err("hot_operation_fraction must be in [0, 1]".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+// Operation mix run against each backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Workload {
+    // Insert every record one by one, read them all back, then clear them
+    InsertRead,
+    // Same as insert_read, but inserted `batch_size` records per round trip
+    BatchInsertRead,
+    // Load every record in one bulk operation, then read back and clear:
+    // Postgres COPY in text or binary format, the batched insert elsewhere
+    BulkLoadText,
+    BulkLoadBinary,
+    // Batch-load the records, then fetch `lookups` random ids one at a time
+    PointLookup,
+    // Batch-load the records, then rewrite `updates` random records in place
+    Update,
+    // Batch-load the records, delete `deletes` distinct records one by one,
+    // then clear the rest; the two kinds of delete are timed separately
+    Delete,
+    // Batch-load the records, build the (active, age) secondary index, then run
+    // `queries` range queries `age BETWEEN x AND x + age_span - 1 AND active`
+    FilteredQuery,
+    // YCSB core workloads over the batch-loaded records, `operations` each:
+    // A update heavy, B read mostly, C read only, D read latest,
+    // E short ranges, F read-modify-write
+    YcsbA,
+    YcsbB,
+    YcsbC,
+    YcsbD,
+    YcsbE,
+    YcsbF,
+}
+
+impl Workload {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Workload::InsertRead => "insert_read",
+            Workload::BatchInsertRead => "batch_insert_read",
+            Workload::BulkLoadText => "bulk_load_text",
+            Workload::BulkLoadBinary => "bulk_load_binary",
+            Workload::PointLookup => "point_lookup",
+            Workload::Update => "update",
+            Workload::Delete => "delete",
+            Workload::FilteredQuery => "filtered_query",
+            Workload::YcsbA => "ycsb_a",
+            Workload::YcsbB => "ycsb_b",
+            Workload::YcsbC => "ycsb_c",
+            Workload::YcsbD => "ycsb_d",
+            Workload::YcsbE => "ycsb_e",
+            Workload::YcsbF => "ycsb_f",
+        }
+    }
+}
+
+// Parameters of one benchmark run, as accepted by POST /benchmark
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BenchmarkConfig {
+    pub record_count: usize,
+    pub dataset: DatasetSource,
+    // Backend keys to run; empty means every configured backend
+    pub backends: Vec<String>,
+    pub workloads: Vec<Workload>,
+    // Unmeasured runs before the repetitions, to warm caches and connections
+    pub warmup: usize,
+    // Measured runs, summarised with mean, spread and confidence interval
+    pub repetitions: usize,
+    // Operations kept in flight at once per backend, in every phase
+    pub concurrency: usize,
+    // Run each workload at 1, 2, 4, ... up to `concurrency` operations in flight
+    pub concurrency_sweep: bool,
+    // Open loop: issue each phase's operations at this many per second, still
+    // capped at `concurrency` in flight, with latency measured from when each
+    // operation was due. Closed loop when absent.
+    pub target_rate: Option<f64>,
+    // Records per round trip in batch_insert_read
+    pub batch_size: usize,
+    // Primary-key reads made by point_lookup
+    pub lookups: usize,
+    // In-place updates made by the update workload
+    pub updates: usize,
+    // Single-record deletes made by the delete workload, at most record_count
+    pub deletes: usize,
+    // Range queries made by filtered_query, each covering `age_span` ages
+    pub queries: usize,
+    pub age_span: u32,
+    // Operations per YCSB workload run
+    pub operations: usize,
+    // Shuffles record order and seeds key choice deterministically when set
+    pub seed: Option<u64>,
+    // Key choice of point_lookup, update and the YCSB workloads; when absent
+    // point_lookup and update are uniform and YCSB uses its standard choice
+    pub distribution: Option<KeyDistribution>,
+    // Compare read-back records against the dataset field by field
+    pub verify: bool,
+    // Extra fields added to every record, loaded or generated; none when absent
+    pub payload: Option<PayloadParams>,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            record_count: NUM_RECORDS,
+            dataset: DatasetSource::default(),
+            backends: Vec::new(),
+            workloads: vec![Workload::InsertRead],
+            warmup: 0,
+            repetitions: 1,
+            concurrency: 1,
+            concurrency_sweep: false,
+            target_rate: None,
+            batch_size: 1_000,
+            lookups: 1_000,
+            updates: 1_000,
+            deletes: 1_000,
+            queries: 100,
+            age_span: 10,
+            operations: 10_000,
+            seed: None,
+            distribution: None,
+            verify: false,
+            payload: None,
+        }
+    }
+}
+
+impl BenchmarkConfig {
+    /// Concurrency levels to run: doubling from 1 up to and including
+    /// `concurrency` when sweeping, otherwise just `concurrency`
+    pub fn concurrency_levels(&self) -> Vec<usize> {
+        if !self.concurrency_sweep {
+            return vec![self.concurrency];
+        }
+        let mut levels: Vec<usize> = std::iter::successors(Some(1), |level| Some(level * 2))
+            .take_while(|&level| level < self.concurrency)
+            .collect();
+        levels.push(self.concurrency);
+        levels
+    }
+
+    // Operations in the longest paced phase of the selected workloads;
+    // inserts count once per record, as in insert_read
+    fn paced_operations(&self) -> usize {
+        self.workloads
+            .iter()
+            .map(|workload| match workload {
+                Workload::PointLookup => self.lookups,
+                Workload::Update => self.updates,
+                Workload::Delete => self.deletes,
+                Workload::FilteredQuery => self.queries,
+                Workload::YcsbA
+                | Workload::YcsbB
+                | Workload::YcsbC
+                | Workload::YcsbD
+                | Workload::YcsbE
+                | Workload::YcsbF => self.operations,
+                _ => 0,
+            })
+            .fold(self.record_count, usize::max)
+    }
+
+    /// Check value ranges; backend keys are checked against the registry separately
+    pub fn validate(&self) -> Result<(), String> {
+        if self.record_count == 0 || self.record_count > MAX_RECORDS {
+            return Err(format!(
+                "record_count must be between 1 and {}",
+                MAX_RECORDS
+            ));
+        }
+        if self.repetitions == 0 || self.repetitions > MAX_REPETITIONS {
+            return Err(format!(
+                "repetitions must be between 1 and {}",
+                MAX_REPETITIONS
+            ));
+        }
+        if self.warmup > MAX_WARMUP {
+            return Err(format!("warmup must be at most {}", MAX_WARMUP));
+        }
+        if self.concurrency == 0 || self.concurrency > MAX_CONCURRENCY {
+            return Err(format!(
+                "concurrency must be between 1 and {}",
+                MAX_CONCURRENCY
+            ));
+        }
+        if let Some(rate) = self.target_rate {
+            if !(rate.is_finite() && rate >= MIN_TARGET_RATE) {
+                return Err(format!(
+                    "target_rate must be a number of at least {}",
+                    MIN_TARGET_RATE
+                ));
+            }
+            let operations = self.paced_operations();
+            if operations as f64 / rate > MAX_PACED_PHASE_SECS {
+                return Err(format!(
+                    "target_rate too low: {} operations would take over {} seconds",
+                    operations, MAX_PACED_PHASE_SECS
+                ));
+            }
+        }
+        if self.batch_size == 0 || self.batch_size > MAX_BATCH_SIZE {
+            return Err(format!(
+                "batch_size must be between 1 and {}",
+                MAX_BATCH_SIZE
+            ));
+        }
+        if self.lookups == 0 || self.lookups > MAX_RECORDS {
+            return Err(format!("lookups must be between 1 and {}", MAX_RECORDS));
+        }
+        if self.updates == 0 || self.updates > MAX_RECORDS {
+            return Err(format!("updates must be between 1 and {}", MAX_RECORDS));
+        }
+        if self.deletes == 0 || self.deletes > MAX_RECORDS {
+            return Err(format!("deletes must be between 1 and {}", MAX_RECORDS));
+        }
+        if self.workloads.contains(&Workload::Delete) && self.deletes > self.record_count {
+            return Err("deletes must not exceed record_count".to_string());
+        }
+        if self.queries == 0 || self.queries > MAX_RECORDS {
+            return Err(format!("queries must be between 1 and {}", MAX_RECORDS));
+        }
+        if self.age_span == 0 {
+            return Err("age_span must be at least 1".to_string());
+        }
+        if self.operations == 0 || self.operations > MAX_RECORDS {
+            return Err(format!("operations must be between 1 and {}", MAX_RECORDS));
+        }
+        if let Some(distribution) = &self.distribution {
+            distribution.validate()?;
+        }
+        if let Some(payload) = &self.payload {
+            payload.validate()?;
+            if self.record_count.saturating_mul(payload.size) > MAX_DATASET_PAYLOAD_BYTES {
+                return Err(format!(
+                    "record_count * payload size must be at most {} bytes",
+                    MAX_DATASET_PAYLOAD_BYTES
+                ));
+            }
+            if self.batch_size.saturating_mul(payload.size) > MAX_BATCH_PAYLOAD_BYTES {
+                return Err(format!(
+                    "batch_size * payload size must be at most {} bytes",
+                    MAX_BATCH_PAYLOAD_BYTES
+                ));
+            }
+        }
+        if self.workloads.is_empty() {
+            return Err("workloads must not be empty".to_string());
+        }
+        match &self.dataset {
+            DatasetSource::File { path, .. } if path.trim().is_empty() => {
+                return Err("dataset path must not be empty".to_string());
+            }
+            DatasetSource::File { path, .. } => {
+                dataset_path(path)?;
+            }
+            DatasetSource::Generated(params) => params.validate()?,
+        }
+        Ok(())
+    }
+}
@@ -1,116 +1,241 @@
-use axum::{Json, extract::State, http::StatusCode};
-use serde::Serialize;
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, io::ErrorKind};
 
 use crate::{
-    config::NUM_RECORDS,
-    store::{shared_state::AppState, users::Users},
+    config::{BenchmarkConfig, DatasetSource, FileFormat, dataset_path},
+    store::{
+        backend_registry::parse_backend_list,
+        error::Error,
+        runs::Progress,
+        shared_state::AppState,
+        statistics::{MetricStats, summarize_runs},
+        users::{BenchmarkResult, Users},
+    },
 };
 
 #[derive(Serialize)]
 pub struct BenchmarkResponse {
     database: String,
-    insert_time_s: f64,
-    read_time_s: f64,
-    clear_time_s: f64,
-    entries: usize,
+    // "ok", "failed" when the run errored, its counts disagree or verification
+    // found bad records, or "unavailable" when the backend could not be connected
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    workload: Option<String>,
+    // Operations kept in flight during the runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    concurrency: Option<usize>,
+    // Connections the backend's pool allows, when `concurrency` exceeds it:
+    // the runs then partly measure waiting for a connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connection_limit: Option<usize>,
+    // Discarded runs made before the measured ones
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warmup: Option<usize>,
+    // Measured runs, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    runs: Vec<BenchmarkResult>,
+    // Mean, spread and 95% confidence interval of each metric across `runs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<BTreeMap<String, MetricStats>>,
+}
+
+#[derive(Deserialize)]
+pub struct BenchmarkQuery {
+    // Comma-separated backend keys, e.g. `rocksdb,leveldb`; all when absent
+    backends: Option<String>,
 }
 
+/// GET /benchmark: default parameters, optionally restricted to `?backends=`
 pub async fn benchmark_handler(
     State(state): State<AppState>,
-) -> Result<Json<Vec<BenchmarkResponse>>, StatusCode> {
-    // Load users data
-    let users = match Users::load("users.json") {
-        Ok(users) => users,
-        Err(e) => {
-            eprintln!("Failed to load users: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
+    Query(query): Query<BenchmarkQuery>,
+) -> Result<Json<Vec<BenchmarkResponse>>, (StatusCode, String)> {
+    let config = BenchmarkConfig {
+        backends: query
+            .backends
+            .as_deref()
+            .map(parse_backend_list)
+            .unwrap_or_default(),
+        ..BenchmarkConfig::default()
     };
 
-    let mut results = Vec::new();
+    run_benchmark(&state, config, &Progress::default())
+        .await
+        .map(Json)
+}
 
-    // Run PostgreSQL benchmark - clean API!
-    let result = match users.postgres_benchmark(&state.pg_pool).await {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("Benchmark failed: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
+/// POST /benchmark: parameters taken from a JSON `BenchmarkConfig` body
+pub async fn benchmark_config_handler(
+    State(state): State<AppState>,
+    Json(config): Json<BenchmarkConfig>,
+) -> Result<Json<Vec<BenchmarkResponse>>, (StatusCode, String)> {
+    run_benchmark(&state, config, &Progress::default())
+        .await
+        .map(Json)
+}
 
-    results.push(BenchmarkResponse {
-        database: "PostgreSQL".to_string(),
-        insert_time_s: result.insert_time_s,
-        read_time_s: result.read_time_s,
-        clear_time_s: result.clear_time_s,
-        entries: NUM_RECORDS,
-    });
-
-    //MongoDB Benchmark
-    let mongo_result = match users.mongo_benchmark(&state.mongo_db).await {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("Benchmark failed: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
+/// Run `config` once every benchmark ahead of it has finished, reporting
+/// each phase to `progress`
+pub async fn run_benchmark(
+    state: &AppState,
+    config: BenchmarkConfig,
+    progress: &Progress,
+) -> Result<Vec<BenchmarkResponse>, (StatusCode, String)> {
+    if let Err(e) = config.validate() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, e));
+    }
+    let _running = state.benchmark_lock.lock().await;
+
+    let keys: Vec<String> = config
+        .backends
+        .iter()
+        .map(|key| key.trim().to_ascii_lowercase())
+        .collect();
 
-    results.push(BenchmarkResponse {
-        database: "MongoDB".to_string(),
-        insert_time_s: mongo_result.insert_time_s,
-        read_time_s: mongo_result.read_time_s,
-        clear_time_s: mongo_result.clear_time_s,
-        entries: NUM_RECORDS,
-    });
-
-    //SurrealDB Benchmark
-    let surreal_result = match users.surreal_benchmark(&state.surreal_db).await {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("Benchmark failed: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    let backends = match state.registry.select(&keys).await {
+        Ok(backends) => backends,
+        Err(key) => {
+            let message = format!(
+                "Unknown backend '{}', available: {}",
+                key,
+                state.registry.keys().join(",")
+            );
+            eprintln!("{}", message);
+            return Err((StatusCode::BAD_REQUEST, message));
         }
     };
 
-    results.push(BenchmarkResponse {
-        database: "SurrealDB".to_string(),
-        insert_time_s: surreal_result.insert_time_s,
-        read_time_s: surreal_result.read_time_s,
-        clear_time_s: surreal_result.clear_time_s,
-        entries: NUM_RECORDS,
-    });
-
-    // RockDB Benchmark
-    let rocks_result = match users.rocks_benchmark(&state.rocks_db) {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("RocksDB Benchmark failed: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    // Load or generate users data
+    progress.phase("load_dataset");
+    let users = match &config.dataset {
+        DatasetSource::File { path, format } => {
+            let format = format.unwrap_or_else(|| FileFormat::for_path(path));
+            let resolved = dataset_path(path).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+            let users = match Users::load(&resolved, format, config.record_count, config.seed) {
+                Ok(users) => users,
+                Err(e) => {
+                    eprintln!("Failed to load users from {}: {}", resolved.display(), e);
+                    // The file is named by the client, so a missing or unreadable
+                    // dataset is their error rather than the server's
+                    let status = match &e {
+                        Error::Io(io) if io.kind() == ErrorKind::NotFound => StatusCode::NOT_FOUND,
+                        Error::Io(io) if io.kind() != ErrorKind::InvalidData => {
+                            StatusCode::INTERNAL_SERVER_ERROR
+                        }
+                        _ => StatusCode::UNPROCESSABLE_ENTITY,
+                    };
+                    let message = if status == StatusCode::NOT_FOUND {
+                        format!("Dataset file '{}' not found", path)
+                    } else {
+                        format!("Failed to load users: {}", e)
+                    };
+                    return Err((status, message));
+                }
+            };
+
+            if users.len() < config.record_count {
+                return Err((
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!(
+                        "Dataset has {} records, {} requested",
+                        users.len(),
+                        config.record_count
+                    ),
+                ));
+            }
+            users
         }
-    };
-    results.push(BenchmarkResponse {
-        database: "RocksDB".to_string(),
-        insert_time_s: rocks_result.insert_time_s,
-        read_time_s: rocks_result.read_time_s,
-        clear_time_s: rocks_result.clear_time_s,
-        entries: NUM_RECORDS,
-    });
-
-    // LevelDB Benchmark
-    let level_result = match users.level_benchmark(&state.level_db) {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("LevelDB Benchmark failed: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        DatasetSource::Generated(params) => {
+            Users::generate(config.record_count, *params, config.seed)
         }
     };
-    results.push(BenchmarkResponse {
-        database: "LevelDB".to_string(),
-        insert_time_s: level_result.insert_time_s,
-        read_time_s: level_result.read_time_s,
-        clear_time_s: level_result.clear_time_s,
-        entries: NUM_RECORDS,
-    });
-
-    Ok(Json(results))
+    let users = match &config.payload {
+        Some(payload) => users.with_payload(payload, config.seed),
+        None => users,
+    };
+
+    let mut results = Vec::new();
+
+    for handle in backends {
+        let backend = match handle.backend {
+            Ok(backend) => backend,
+            Err(e) => {
+                eprintln!("{} unavailable: {}", handle.name, e);
+                results.push(BenchmarkResponse {
+                    database: handle.name.to_string(),
+                    status: "unavailable".to_string(),
+                    error: Some(e),
+                    workload: None,
+                    concurrency: None,
+                    connection_limit: None,
+                    warmup: None,
+                    runs: Vec::new(),
+                    summary: None,
+                });
+                continue;
+            }
+        };
+
+        for workload in &config.workloads {
+            for concurrency in config.concurrency_levels() {
+                let config = BenchmarkConfig {
+                    concurrency,
+                    ..config.clone()
+                };
+                let progress = progress.at(backend.name(), workload.as_str(), concurrency);
+                let mut runs = Vec::new();
+                let mut failure = None;
+
+                for iteration in 0..config.warmup + config.repetitions {
+                    let run = users.benchmark(backend.as_ref(), &config, *workload, &progress);
+                    let measured = iteration >= config.warmup;
+
+                    match run.await {
+                        Ok(result) => {
+                            failure = result.failure(users.len());
+                            if measured {
+                                runs.push(result);
+                            }
+                        }
+                        Err(e) => failure = Some(e.to_string()),
+                    }
+
+                    if let Some(e) = &failure {
+                        let phase = if measured { "Benchmark" } else { "Warmup" };
+                        eprintln!(
+                            "{} {} failed at concurrency {}: {}",
+                            backend.name(),
+                            phase,
+                            concurrency,
+                            e
+                        );
+                        break;
+                    }
+                }
+
+                results.push(BenchmarkResponse {
+                    database: backend.name().to_string(),
+                    status: if failure.is_none() { "ok" } else { "failed" }.to_string(),
+                    workload: Some(workload.as_str().to_string()),
+                    concurrency: Some(concurrency),
+                    connection_limit: backend
+                        .connection_limit()
+                        .filter(|&limit| concurrency > limit),
+                    warmup: Some(config.warmup),
+                    summary: failure.is_none().then(|| summarize_runs(&runs)),
+                    error: failure,
+                    runs,
+                });
+            }
+        }
+    }
+
+    Ok(results)
 }
@@ -1,15 +1,77 @@
-use axum::{Json, http::StatusCode};
+use std::time::{Duration, Instant};
+
+use axum::{Json, extract::State, http::StatusCode};
+use futures::future::join_all;
 use serde::Serialize;
 
+use crate::store::{backend_registry::BackendHandle, shared_state::AppState};
+
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+pub struct BackendHealth {
+    database: String,
+    // "ok", "error" when the ping failed, or "unavailable" when never connected
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct HealthResponse {
+    // "ok" when every backend answered, otherwise "degraded"
     status: String,
     version: String,
+    backends: Vec<BackendHealth>,
 }
 
-pub async fn health_check() -> Result<Json<HealthResponse>, StatusCode> {
+async fn check_backend(handle: BackendHandle) -> BackendHealth {
+    let backend = match handle.backend {
+        Ok(backend) => backend,
+        Err(e) => {
+            return BackendHealth {
+                database: handle.name.to_string(),
+                status: "unavailable".to_string(),
+                latency_ms: None,
+                error: Some(e),
+            };
+        }
+    };
+
+    let start = Instant::now();
+    let result = tokio::time::timeout(PING_TIMEOUT, backend.ping()).await;
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let error = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("ping timed out after {:?}", PING_TIMEOUT)),
+    };
+
+    BackendHealth {
+        database: handle.name.to_string(),
+        status: if error.is_none() { "ok" } else { "error" }.to_string(),
+        latency_ms: Some(latency_ms),
+        error,
+    }
+}
+
+pub async fn health_check(
+    State(state): State<AppState>,
+) -> Result<Json<HealthResponse>, StatusCode> {
+    let backends = join_all(state.registry.snapshot().into_iter().map(check_backend)).await;
+
+    let status = if backends.iter().all(|b| b.status == "ok") {
+        "ok"
+    } else {
+        "degraded"
+    };
+
     Ok(Json(HealthResponse {
-        status: "ok".to_string(),
-        version: "1.0.0".to_string(),
+        status: status.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        backends,
     }))
 }
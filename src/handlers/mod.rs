@@ -1,2 +1,3 @@
 pub mod benchmark_handler;
 pub mod health;
+pub mod runs_handler;
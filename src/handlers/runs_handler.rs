@@ -0,0 +1,73 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+
+use crate::{
+    config::BenchmarkConfig,
+    handlers::benchmark_handler::run_benchmark,
+    store::{
+        runs::{MAX_PENDING_RUNS, Progress, Run},
+        shared_state::AppState,
+    },
+};
+
+/// POST /runs: queue a benchmark and return its id at once; the run carries on
+/// whether or not the client stays connected
+pub async fn create_run_handler(
+    State(state): State<AppState>,
+    Json(config): Json<BenchmarkConfig>,
+) -> Result<(StatusCode, Json<Run>), (StatusCode, String)> {
+    if let Err(e) = config.validate() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, e));
+    }
+
+    let Some(run) = state.runs.create() else {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            format!(
+                "{} runs are already queued or running; poll them before adding more",
+                MAX_PENDING_RUNS
+            ),
+        ));
+    };
+    let id = run.id.clone();
+
+    let runs = state.runs.clone();
+    let job = {
+        let id = id.clone();
+        async move {
+            let progress = Progress::for_run(state.runs.clone(), id);
+            match run_benchmark(&state, config, &progress).await {
+                Ok(results) => serde_json::to_value(results).map_err(|e| e.to_string()),
+                Err((_, message)) => Err(message),
+            }
+        }
+    };
+    tokio::spawn(async move {
+        // A panicking benchmark still ends its run instead of leaving it running
+        let outcome = match tokio::spawn(job).await {
+            Ok(outcome) => outcome,
+            Err(e) => Err(format!("Benchmark task failed: {}", e)),
+        };
+        if let Err(e) = &outcome {
+            eprintln!("Run {} failed: {}", id, e);
+        }
+        runs.finish(&id, outcome);
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(run)))
+}
+
+/// GET /runs/{id}: status of a run, with its results once done
+pub async fn get_run_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Run>, (StatusCode, String)> {
+    state
+        .runs
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Unknown run '{}'", id)))
+}
@@ -4,11 +4,12 @@ mod routers;
 mod store;
 mod utils;
 
-use axum::{Router, routing::get};
+use axum::Router;
 
 use crate::{
-    handlers::health::health_check, routers::benchmark::benchmark_router,
-    store::shared_state::AppState, utils::db_functions::Databases,
+    routers::{benchmark::benchmark_router, health::health_router, runs::runs_router},
+    store::shared_state::AppState,
+    utils::db_functions::Databases,
 };
 
 #[tokio::main]
@@ -17,25 +18,14 @@ async fn main() {
     //connect to Databases
     let db = Databases::new().await;
 
-    //Tables Setup
-    match db.postgres_tables_setup().await {
-        Ok(_) => println!("Postgres Tables Setup Successfull"),
-        Err(e) => println!("Postgres Tables Setup Failed: {}", e),
-    };
-
     //Shared State
-    let state = AppState::new(
-        db.pg_pool,
-        db.mongo_db_connection,
-        db.surreal_db_connection,
-        db.rocks_db_connection,
-        db.level_db_connection,
-    );
+    let state = AppState::new(db.registry);
 
     //Router setup
     let app = Router::new()
-        .route("/health", get(health_check))
-        .nest("/benchmark", benchmark_router(state.clone()));
+        .nest("/health", health_router(state.clone()))
+        .nest("/benchmark", benchmark_router(state.clone()))
+        .nest("/runs", runs_router(state.clone()));
 
     println!("🚀 Server running on http://localhost:3000");
 
@@ -1,9 +1,12 @@
 use axum::{Router, routing::get};
 
-use crate::{handlers::benchmark_handler::benchmark_handler, store::shared_state::AppState};
+use crate::{
+    handlers::benchmark_handler::{benchmark_config_handler, benchmark_handler},
+    store::shared_state::AppState,
+};
 
 pub fn benchmark_router(state: AppState) -> Router {
     Router::new()
-        .route("/", get(benchmark_handler))
+        .route("/", get(benchmark_handler).post(benchmark_config_handler))
         .with_state(state)
 }
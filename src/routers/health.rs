@@ -0,0 +1,9 @@
+use axum::{Router, routing::get};
+
+use crate::{handlers::health::health_check, store::shared_state::AppState};
+
+pub fn health_router(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(health_check))
+        .with_state(state)
+}
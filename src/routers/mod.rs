@@ -1 +1,3 @@
 pub mod benchmark;
+pub mod health;
+pub mod runs;
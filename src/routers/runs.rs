@@ -0,0 +1,16 @@
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+use crate::{
+    handlers::runs_handler::{create_run_handler, get_run_handler},
+    store::shared_state::AppState,
+};
+
+pub fn runs_router(state: AppState) -> Router {
+    Router::new()
+        .route("/", post(create_run_handler))
+        .route("/:id", get(get_run_handler))
+        .with_state(state)
+}
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use tokio::sync::Mutex;
+
+use crate::{store::error::BoxError, utils::db_operations::storage_backend::StorageBackend};
+
+pub type ConnectFuture = BoxFuture<'static, Result<Arc<dyn StorageBackend>, BoxError>>;
+
+// Knows how to open one backend; `key` is the name used to select it
+#[derive(Clone, Copy)]
+pub struct BackendConnector {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub connect: fn() -> ConnectFuture,
+}
+
+impl BackendConnector {
+    /// Connect and run the backend's schema setup
+    async fn open(&self) -> Result<Arc<dyn StorageBackend>, BoxError> {
+        let backend = (self.connect)().await?;
+
+        // A failed setup is reported but does not make the backend unavailable
+        match backend.setup().await {
+            Ok(_) => println!("{} Setup Successfull", self.name),
+            Err(e) => println!("{} Setup Failed: {}", self.name, e),
+        };
+
+        Ok(backend)
+    }
+}
+
+enum BackendStatus {
+    Connected(Arc<dyn StorageBackend>),
+    // Last connection error; retried on next use
+    Unavailable(String),
+}
+
+struct Entry {
+    connector: BackendConnector,
+    status: Mutex<BackendStatus>,
+}
+
+impl Entry {
+    /// The connected backend, reconnecting first if the last attempt failed
+    async fn resolve(&self) -> Result<Arc<dyn StorageBackend>, String> {
+        let mut status = self.status.lock().await;
+
+        if let BackendStatus::Connected(backend) = &*status {
+            return Ok(backend.clone());
+        }
+
+        match self.connector.open().await {
+            Ok(backend) => {
+                eprintln!("Connected to {}", self.connector.name);
+                *status = BackendStatus::Connected(backend.clone());
+                Ok(backend)
+            }
+            Err(e) => {
+                let message = e.to_string();
+                *status = BackendStatus::Unavailable(message.clone());
+                Err(message)
+            }
+        }
+    }
+}
+
+// A registered backend, or the reason it cannot currently be used
+pub struct BackendHandle {
+    pub name: &'static str,
+    pub backend: Result<Arc<dyn StorageBackend>, String>,
+}
+
+// Configured backends keyed by name, kept in registration order
+#[derive(Clone, Default)]
+pub struct BackendRegistry {
+    entries: Arc<Vec<Entry>>,
+}
+
+impl BackendRegistry {
+    /// Try every connector once; failures are kept as unavailable entries
+    pub async fn connect(connectors: impl IntoIterator<Item = BackendConnector>) -> Self {
+        let mut entries = Vec::new();
+
+        for connector in connectors {
+            let status = match connector.open().await {
+                Ok(backend) => {
+                    eprintln!("Connected to {}", connector.name);
+                    BackendStatus::Connected(backend)
+                }
+                Err(e) => {
+                    eprintln!("Failed to connect to {}: {}", connector.name, e);
+                    BackendStatus::Unavailable(e.to_string())
+                }
+            };
+
+            entries.push(Entry {
+                connector,
+                status: Mutex::new(status),
+            });
+        }
+
+        Self {
+            entries: Arc::new(entries),
+        }
+    }
+
+    pub fn keys(&self) -> Vec<&'static str> {
+        self.entries.iter().map(|e| e.connector.key).collect()
+    }
+
+    fn entry(&self, key: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|e| e.connector.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Current state of every backend without attempting to reconnect
+    pub fn snapshot(&self) -> Vec<BackendHandle> {
+        let mut handles = Vec::new();
+        for entry in self.entries.iter() {
+            // A held lock means a reconnect is running; don't wait for it
+            let backend = match entry.status.try_lock().as_deref() {
+                Ok(BackendStatus::Connected(backend)) => Ok(backend.clone()),
+                Ok(BackendStatus::Unavailable(e)) => Err(e.clone()),
+                Err(_) => Err("reconnect in progress".to_string()),
+            };
+            handles.push(BackendHandle {
+                name: entry.connector.name,
+                backend,
+            });
+        }
+        handles
+    }
+
+    /// Resolve the requested keys in order, or every backend when none are given,
+    /// retrying any that are unavailable. Fails with the first key that is not registered.
+    pub async fn select(&self, keys: &[String]) -> Result<Vec<BackendHandle>, String> {
+        let entries: Vec<&Entry> = if keys.is_empty() {
+            self.entries.iter().collect()
+        } else {
+            keys.iter()
+                .map(|key| self.entry(key).ok_or_else(|| key.clone()))
+                .collect::<Result<_, _>>()?
+        };
+
+        let mut selected = Vec::new();
+        for entry in entries {
+            selected.push(BackendHandle {
+                name: entry.connector.name,
+                backend: entry.resolve().await,
+            });
+        }
+        Ok(selected)
+    }
+}
+
+/// Split a comma-separated backend list such as `rocksdb, leveldb`
+pub fn parse_backend_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|key| key.trim().to_ascii_lowercase())
+        .filter(|key| !key.is_empty())
+        .collect()
+}
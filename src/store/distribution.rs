@@ -0,0 +1,168 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::config::KeyDistribution;
+
+/// Seeded generator when a seed is given, otherwise seeded from the OS
+pub fn rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+// Zipfian ranks over `0..items` (rank 0 most popular), after Gray et al.,
+// "Quickly Generating Billion-Record Synthetic Databases", as used by YCSB.
+// The item count may grow between draws; zeta is extended incrementally.
+pub struct Zipfian {
+    theta: f64,
+    alpha: f64,
+    zeta2: f64,
+    zetan: f64,
+    items: usize,
+}
+
+fn zeta(from: usize, to: usize, theta: f64, initial: f64) -> f64 {
+    (from..to).fold(initial, |sum, i| sum + 1.0 / ((i + 1) as f64).powf(theta))
+}
+
+impl Zipfian {
+    pub fn new(items: usize, theta: f64) -> Self {
+        Self {
+            theta,
+            alpha: 1.0 / (1.0 - theta),
+            zeta2: zeta(0, 2, theta, 0.0),
+            zetan: zeta(0, items, theta, 0.0),
+            items,
+        }
+    }
+
+    /// Rank in `0..items`; `items` must not shrink between calls
+    pub fn next(&mut self, rng: &mut impl Rng, items: usize) -> usize {
+        if items > self.items {
+            self.zetan = zeta(self.items, items, self.theta, self.zetan);
+            self.items = items;
+        }
+
+        let n = self.items as f64;
+        let eta = (1.0 - (2.0 / n).powf(1.0 - self.theta)) / (1.0 - self.zeta2 / self.zetan);
+        let u: f64 = rng.gen();
+        let uz = u * self.zetan;
+        if uz < 1.0 {
+            return 0;
+        }
+        if uz < 1.0 + 0.5f64.powf(self.theta) {
+            return 1.min(self.items - 1);
+        }
+        ((n * (eta * u - eta + 1.0).powf(self.alpha)) as usize).min(self.items - 1)
+    }
+}
+
+/// Spread zipfian ranks over the key space so popular items are not adjacent
+pub fn scramble(rank: usize, items: usize) -> usize {
+    let hash = (rank as u64)
+        .to_le_bytes()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    (hash % items as u64) as usize
+}
+
+// Draws record indices following a `KeyDistribution`
+pub struct KeyChooser {
+    distribution: KeyDistribution,
+    zipfian: Option<Zipfian>,
+}
+
+impl KeyChooser {
+    pub fn new(distribution: KeyDistribution, items: usize) -> Self {
+        let zipfian = match distribution {
+            KeyDistribution::Zipfian { theta } | KeyDistribution::Latest { theta } => {
+                Some(Zipfian::new(items, theta))
+            }
+            KeyDistribution::Uniform | KeyDistribution::Hotspot { .. } => None,
+        };
+        Self {
+            distribution,
+            zipfian,
+        }
+    }
+
+    /// Index in `0..items`; `items` may grow between calls, and `Latest`
+    /// treats the highest indices as the most recent records
+    pub fn next(&mut self, rng: &mut impl Rng, items: usize) -> usize {
+        match (self.distribution, self.zipfian.as_mut()) {
+            (KeyDistribution::Zipfian { .. }, Some(zipfian)) => {
+                scramble(zipfian.next(rng, items), items)
+            }
+            (KeyDistribution::Latest { .. }, Some(zipfian)) => items - 1 - zipfian.next(rng, items),
+            (
+                KeyDistribution::Hotspot {
+                    hot_set_fraction,
+                    hot_operation_fraction,
+                },
+                _,
+            ) => {
+                let hot = ((items as f64 * hot_set_fraction) as usize).clamp(1, items);
+                if hot == items || rng.gen::<f64>() < hot_operation_fraction {
+                    rng.gen_range(0..hot)
+                } else {
+                    rng.gen_range(hot..items)
+                }
+            }
+            _ => rng.gen_range(0..items),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ITEMS: usize = 10_000;
+    const DRAWS: usize = 100_000;
+
+    fn draws(distribution: KeyDistribution, seed: u64) -> Vec<usize> {
+        let mut keys = KeyChooser::new(distribution, ITEMS);
+        let mut rng = rng(Some(seed));
+        (0..DRAWS).map(|_| keys.next(&mut rng, ITEMS)).collect()
+    }
+
+    fn most_frequent_share(draws: &[usize]) -> f64 {
+        let mut counts = vec![0usize; ITEMS];
+        for &key in draws {
+            counts[key] += 1;
+        }
+        *counts.iter().max().unwrap() as f64 / draws.len() as f64
+    }
+
+    #[test]
+    fn same_seed_gives_same_keys() {
+        let zipfian = KeyDistribution::Zipfian { theta: 0.99 };
+        assert_eq!(draws(zipfian, 7), draws(zipfian, 7));
+        assert_ne!(draws(zipfian, 7), draws(zipfian, 8));
+    }
+
+    #[test]
+    fn distributions_have_their_shape() {
+        let uniform = draws(KeyDistribution::Uniform, 1);
+        let zipfian = draws(KeyDistribution::Zipfian { theta: 0.99 }, 1);
+        let latest = draws(KeyDistribution::Latest { theta: 0.99 }, 1);
+        let hotspot = draws(
+            KeyDistribution::Hotspot {
+                hot_set_fraction: 0.2,
+                hot_operation_fraction: 0.8,
+            },
+            1,
+        );
+
+        assert!(uniform.iter().all(|&key| key < ITEMS));
+        assert!(most_frequent_share(&uniform) < 0.001);
+        // The top rank takes 1/zeta(n) of the draws, about 10% here
+        assert!(most_frequent_share(&zipfian) > 0.05);
+        let newest = latest.iter().filter(|&&key| key == ITEMS - 1).count();
+        assert!(newest as f64 / DRAWS as f64 > 0.05);
+        let hot = hotspot.iter().filter(|&&key| key < ITEMS / 5).count();
+        assert!((hot as f64 / DRAWS as f64 - 0.8).abs() < 0.01);
+    }
+}
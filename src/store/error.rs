@@ -1,56 +1,68 @@
 use core::fmt;
 
-use crate::utils::db_operations::{
-    level_db_operations::LevelError, rocks_db_operations::RocksError,
-};
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
+#[derive(Debug)]
 pub enum Error {
-    IoError(std::io::Error),
-    SerdeError(serde_json::Error),
-    SqlxError(sqlx::Error),
-    MongoError(mongodb::error::Error),
-    SurrealError(surrealdb::Error),
-    RocksError(RocksError),
-    LevelError(LevelError),
+    Io(std::io::Error),
+    Serde(serde_json::Error),
+    // A dataset record that could not be parsed, by 1-based line number
+    Malformed {
+        line: usize,
+        reason: String,
+    },
+    // An open-loop due time past what the clock can represent
+    Schedule {
+        issued: u64,
+        rate: f64,
+    },
+    // Any failure raised by a storage backend, tagged with the backend name
+    Backend {
+        backend: &'static str,
+        source: BoxError,
+    },
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Error::IoError(e) => write!(f, "IO error: {}", e),
-            Error::SerdeError(e) => write!(f, "Serialization error: {}", e),
-            Error::SqlxError(e) => write!(f, "SQLx error: {}", e),
-            Error::MongoError(e) => write!(f, "MongoDB error: {}", e),
-            Error::SurrealError(e) => write!(f, "SurrealDB error: {}", e),
-            Error::RocksError(e) => write!(f, "RocksDB error: {}", e),
-            Error::LevelError(e) => write!(f, "LevelDB error: {}", e),
+impl Error {
+    pub fn malformed(line: usize, reason: impl ToString) -> Self {
+        Error::Malformed {
+            line,
+            reason: reason.to_string(),
         }
     }
-}
 
-impl From<sqlx::Error> for Error {
-    fn from(err: sqlx::Error) -> Self {
-        Error::SqlxError(err)
-    }
-}
-impl From<mongodb::error::Error> for Error {
-    fn from(err: mongodb::error::Error) -> Self {
-        Error::MongoError(err)
+    pub fn backend(backend: &'static str, source: impl Into<BoxError>) -> Self {
+        Error::Backend {
+            backend,
+            source: source.into(),
+        }
     }
 }
 
-impl From<surrealdb::Error> for Error {
-    fn from(err: surrealdb::Error) -> Self {
-        Error::SurrealError(err)
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "IO error: {}", e),
+            Error::Serde(e) => write!(f, "Serialization error: {}", e),
+            Error::Malformed { line, reason } => write!(f, "line {}: {}", line, reason),
+            Error::Schedule { issued, rate } => write!(
+                f,
+                "operation {} at {} ops/s is due beyond the clock's range",
+                issued, rate
+            ),
+            Error::Backend { backend, source } => write!(f, "{} error: {}", backend, source),
+        }
     }
 }
-impl From<RocksError> for Error {
-    fn from(err: RocksError) -> Self {
-        Error::RocksError(err)
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
     }
 }
-impl From<LevelError> for Error {
-    fn from(err: LevelError) -> Self {
-        Error::LevelError(err)
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Serde(err)
     }
 }
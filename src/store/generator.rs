@@ -0,0 +1,211 @@
+use rand::{Rng, rngs::StdRng};
+
+use crate::{
+    config::GeneratorParams,
+    store::{distribution::rng, user_struct::User},
+};
+
+const FIRST_NAMES: [&str; 32] = [
+    "James",
+    "Mary",
+    "Robert",
+    "Patricia",
+    "John",
+    "Jennifer",
+    "Michael",
+    "Linda",
+    "David",
+    "Elizabeth",
+    "William",
+    "Barbara",
+    "Richard",
+    "Susan",
+    "Joseph",
+    "Jessica",
+    "Thomas",
+    "Sarah",
+    "Carlos",
+    "Maria",
+    "Wei",
+    "Yuki",
+    "Ahmed",
+    "Fatima",
+    "Ivan",
+    "Olga",
+    "Kwame",
+    "Amara",
+    "Luca",
+    "Sofia",
+    "Arjun",
+    "Priya",
+];
+
+const LAST_NAMES: [&str; 32] = [
+    "Smith",
+    "Johnson",
+    "Williams",
+    "Brown",
+    "Jones",
+    "Garcia",
+    "Miller",
+    "Davis",
+    "Rodriguez",
+    "Martinez",
+    "Hernandez",
+    "Lopez",
+    "Wilson",
+    "Anderson",
+    "Taylor",
+    "Thomas",
+    "Moore",
+    "Martin",
+    "Lee",
+    "Chen",
+    "Wang",
+    "Tanaka",
+    "Kim",
+    "Nguyen",
+    "Ivanova",
+    "Petrov",
+    "Mensah",
+    "Okafor",
+    "Rossi",
+    "Schmidt",
+    "Patel",
+    "Sharma",
+];
+
+const EMAIL_DOMAINS: [&str; 6] = [
+    "example.com",
+    "example.org",
+    "example.net",
+    "mail.example.com",
+    "corp.example.com",
+    "users.example.org",
+];
+
+// splitmix64 finalizer: a bijection on u64, so distinct inputs never collide
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+// Streams seeded, realistic `User` records one at a time; take as many as
+// needed. Ids are v4 UUIDs that carry the record's index through a bijection,
+// so they never repeat within a generator.
+pub struct UserGenerator {
+    params: GeneratorParams,
+    rng: StdRng,
+    // Per-generator salt, so different seeds give different ids
+    salt: u64,
+    index: u64,
+}
+
+impl UserGenerator {
+    pub fn new(params: GeneratorParams, seed: Option<u64>) -> Self {
+        let mut rng = rng(seed);
+        Self {
+            params,
+            salt: rng.gen(),
+            rng,
+            index: 0,
+        }
+    }
+
+    fn id(&mut self) -> String {
+        let unique = mix(self.index ^ self.salt).to_be_bytes();
+        let mut bytes: [u8; 16] = self.rng.gen();
+        // Bytes 6 and 8 carry the version and variant; keep the unique part clear of them
+        bytes[..6].copy_from_slice(&unique[..6]);
+        bytes[14..].copy_from_slice(&unique[6..]);
+        uuid::Builder::from_random_bytes(bytes)
+            .into_uuid()
+            .to_string()
+    }
+
+    // Normal around `age_mean` (Box-Muller), clamped to the allowed range
+    fn age(&mut self) -> i32 {
+        let u1: f64 = 1.0 - self.rng.gen::<f64>();
+        let u2: f64 = self.rng.gen();
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+        let age = (self.params.age_mean + z * self.params.age_stddev).round();
+        (age as i32).clamp(self.params.min_age, self.params.max_age)
+    }
+}
+
+impl Iterator for UserGenerator {
+    type Item = User;
+
+    fn next(&mut self) -> Option<User> {
+        let first = FIRST_NAMES[self.rng.gen_range(0..FIRST_NAMES.len())];
+        let last = LAST_NAMES[self.rng.gen_range(0..LAST_NAMES.len())];
+        let domain = EMAIL_DOMAINS[self.rng.gen_range(0..EMAIL_DOMAINS.len())];
+        let user = User {
+            id: self.id(),
+            name: format!("{} {}", first, last),
+            // The index keeps emails unique where names repeat
+            email: format!(
+                "{}.{}{}@{}",
+                first.to_ascii_lowercase(),
+                last.to_ascii_lowercase(),
+                self.index,
+                domain
+            ),
+            age: self.age(),
+            active: self.rng.gen_bool(self.params.active_ratio),
+            padding: None,
+            profile: None,
+            tags: None,
+            blob: None,
+        };
+        self.index += 1;
+        Some(user)
+    }
+}
+
+/// `count` users from the default generator seeded with `seed`, for tests
+#[cfg(test)]
+pub fn test_users(count: usize, seed: u64) -> Vec<User> {
+    UserGenerator::new(GeneratorParams::default(), Some(seed))
+        .take(count)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_records() {
+        let ids = |users: Vec<User>| users.into_iter().map(|u| u.id).collect::<Vec<_>>();
+        assert_eq!(ids(test_users(100, 3)), ids(test_users(100, 3)));
+        assert_ne!(ids(test_users(100, 3)), ids(test_users(100, 4)));
+    }
+
+    #[test]
+    fn records_are_unique_and_follow_the_params() {
+        let params = GeneratorParams::default();
+        let users = test_users(50_000, 1);
+
+        let ids: HashSet<&str> = users.iter().map(|u| u.id.as_str()).collect();
+        let emails: HashSet<&str> = users.iter().map(|u| u.email.as_str()).collect();
+        assert_eq!(ids.len(), users.len());
+        assert_eq!(emails.len(), users.len());
+        assert!(users.iter().all(|u| Uuid::parse_str(&u.id).is_ok()));
+
+        assert!(
+            users
+                .iter()
+                .all(|u| (params.min_age..=params.max_age).contains(&u.age))
+        );
+        let mean_age = users.iter().map(|u| u.age as f64).sum::<f64>() / users.len() as f64;
+        assert!((mean_age - params.age_mean).abs() < 1.0);
+        let active = users.iter().filter(|u| u.active).count() as f64 / users.len() as f64;
+        assert!((active - params.active_ratio).abs() < 0.01);
+    }
+}
@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+// HDR-style log-linear buckets: values below 2^SUB_BUCKET_BITS are exact, larger
+// values keep SUB_BUCKET_BITS significant bits, so a bucket is at most 1/128 of
+// its values wide and its midpoint is within 0.4% of anything in it
+const SUB_BUCKET_BITS: u32 = 8;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+const HALF_SUB_BUCKETS: u64 = SUB_BUCKETS / 2;
+const BUCKETS: usize = (SUB_BUCKETS + (64 - SUB_BUCKET_BITS as u64) * HALF_SUB_BUCKETS) as usize;
+
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKETS {
+        return value as usize;
+    }
+    let shift = (64 - value.leading_zeros()) - SUB_BUCKET_BITS;
+    let top = value >> shift;
+    (SUB_BUCKETS + (shift as u64 - 1) * HALF_SUB_BUCKETS + (top - HALF_SUB_BUCKETS)) as usize
+}
+
+// Smallest and largest values that fall into the bucket
+fn bucket_bounds(index: usize) -> (u64, u64) {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return (index, index);
+    }
+    let offset = index - SUB_BUCKETS;
+    let shift = offset / HALF_SUB_BUCKETS + 1;
+    let top = offset % HALF_SUB_BUCKETS + HALF_SUB_BUCKETS;
+    (top << shift, (top << shift) + ((1u64 << shift) - 1))
+}
+
+// Value reported for the bucket: its midpoint, so estimates are not biased up
+fn bucket_midpoint(index: usize) -> u64 {
+    let (low, high) = bucket_bounds(index);
+    low + (high - low) / 2
+}
+
+// Latency distribution of individual operations, in nanoseconds
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    min: u64,
+    max: u64,
+    sum: u128,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; BUCKETS],
+            count: 0,
+            min: u64::MAX,
+            max: 0,
+            sum: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.counts[bucket_index(nanos)] += 1;
+        self.count += 1;
+        self.min = self.min.min(nanos);
+        self.max = self.max.max(nanos);
+        self.sum += nanos as u128;
+    }
+
+    /// Value at quantile `q` in `0.0..=1.0`, in nanoseconds
+    pub fn value_at_quantile(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_midpoint(index).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Percentiles over the recorded operations; throughput uses the phase wall time
+    pub fn summary(&self, elapsed: Duration) -> PhaseSummary {
+        let micros = |nanos: u64| nanos as f64 / 1_000.0;
+        let elapsed_s = elapsed.as_secs_f64();
+
+        PhaseSummary {
+            ops: self.count,
+            ops_per_sec: if elapsed_s > 0.0 {
+                self.count as f64 / elapsed_s
+            } else {
+                0.0
+            },
+            mean_us: if self.count > 0 {
+                micros((self.sum / self.count as u128) as u64)
+            } else {
+                0.0
+            },
+            min_us: micros(if self.count > 0 { self.min } else { 0 }),
+            p50_us: micros(self.value_at_quantile(0.50)),
+            p90_us: micros(self.value_at_quantile(0.90)),
+            p99_us: micros(self.value_at_quantile(0.99)),
+            p999_us: micros(self.value_at_quantile(0.999)),
+            max_us: micros(self.max),
+        }
+    }
+}
+
+// Latency percentiles (microseconds) and throughput of one benchmark phase
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseSummary {
+    pub ops: u64,
+    pub ops_per_sec: f64,
+    pub mean_us: f64,
+    pub min_us: f64,
+    pub p50_us: f64,
+    pub p90_us: f64,
+    pub p99_us: f64,
+    pub p999_us: f64,
+    pub max_us: f64,
+}
+
+impl PhaseSummary {
+    /// Timing fields by name, for aggregating across repetitions
+    pub fn metrics(&self) -> [(&'static str, f64); 8] {
+        [
+            ("ops_per_sec", self.ops_per_sec),
+            ("mean_us", self.mean_us),
+            ("min_us", self.min_us),
+            ("p50_us", self.p50_us),
+            ("p90_us", self.p90_us),
+            ("p99_us", self.p99_us),
+            ("p999_us", self.p999_us),
+            ("max_us", self.max_us),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_cover_every_value_within_tolerance() {
+        let mut values: Vec<u64> = (0..2_000).collect();
+        for bit in 0..64 {
+            let power = 1u64 << bit;
+            values.extend([power - 1, power, power + 1, power | (power >> 1)]);
+        }
+        values.push(u64::MAX);
+
+        for value in values {
+            let index = bucket_index(value);
+            let (low, high) = bucket_bounds(index);
+            assert!(index < BUCKETS, "{} -> {}", value, index);
+            assert!(
+                low <= value && value <= high,
+                "{} in {}..={}",
+                value,
+                low,
+                high
+            );
+            assert!(
+                high - low <= value >> (SUB_BUCKET_BITS - 1),
+                "{} in {}..={}",
+                value,
+                low,
+                high
+            );
+        }
+        assert_eq!(bucket_index(u64::MAX), BUCKETS - 1);
+        assert_eq!(bucket_bounds(BUCKETS - 1).1, u64::MAX);
+    }
+
+    #[test]
+    fn buckets_are_contiguous() {
+        for index in 0..BUCKETS - 1 {
+            assert_eq!(bucket_index(bucket_bounds(index).1 + 1), index + 1);
+        }
+    }
+
+    #[test]
+    fn quantiles_are_within_half_a_bucket() {
+        let mut histogram = LatencyHistogram::default();
+        for micros in 1..=10_000u64 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        for (q, expected) in [(0.5, 5_000_000.0), (0.9, 9_000_000.0), (0.99, 9_900_000.0)] {
+            let value = histogram.value_at_quantile(q) as f64;
+            assert!(
+                (value - expected).abs() / expected < 0.004,
+                "{}: {}",
+                q,
+                value
+            );
+        }
+    }
+}
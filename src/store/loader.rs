@@ -0,0 +1,562 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{
+    config::FileFormat,
+    store::{error::Error, user_struct::User},
+};
+
+type Records = Box<dyn Iterator<Item = Result<User, Error>>>;
+
+/// Records of a dataset file, parsed one at a time so memory stays bounded by
+/// the largest record rather than the file
+pub fn records(path: &Path, format: FileFormat) -> Result<Records, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(match format {
+        FileFormat::Json => Box::new(JsonArrayRecords::new(reader)),
+        FileFormat::Ndjson => Box::new(NdjsonRecords::new(reader)),
+        FileFormat::Csv => Box::new(CsvRecords::new(reader)),
+    })
+}
+
+// One JSON record per line; blank lines are skipped
+pub struct NdjsonRecords<R> {
+    reader: R,
+    line: usize,
+    buf: String,
+}
+
+impl<R: BufRead> NdjsonRecords<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            buf: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for NdjsonRecords<R> {
+    type Item = Result<User, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => self.line += 1,
+                Err(e) => {
+                    self.line += 1;
+                    return Some(Err(Error::malformed(self.line, e)));
+                }
+            }
+            let record = self.buf.trim();
+            if !record.is_empty() {
+                return Some(
+                    serde_json::from_str(record).map_err(|e| Error::malformed(self.line, e)),
+                );
+            }
+        }
+    }
+}
+
+// The elements of one top-level JSON array, each cut out by tracking nesting
+// and strings, then deserialized on its own
+pub struct JsonArrayRecords<R> {
+    reader: R,
+    line: usize,
+    started: bool,
+    // An element was read, so a comma or the closing bracket comes next
+    expect_separator: bool,
+    // A comma was read, so another element must follow
+    expect_element: bool,
+    done: bool,
+    element: Vec<u8>,
+}
+
+impl<R: BufRead> JsonArrayRecords<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 1,
+            started: false,
+            expect_separator: false,
+            expect_element: false,
+            done: false,
+            element: Vec::new(),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, Error> {
+        let buf = self
+            .reader
+            .fill_buf()
+            .map_err(|e| Error::malformed(self.line, e))?;
+        Ok(buf.first().copied())
+    }
+
+    fn bump(&mut self) -> Result<Option<u8>, Error> {
+        let Some(byte) = self.peek()? else {
+            return Ok(None);
+        };
+        self.reader.consume(1);
+        if byte == b'\n' {
+            self.line += 1;
+        }
+        Ok(Some(byte))
+    }
+
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, Error> {
+        while let Some(byte) = self.peek()? {
+            if !byte.is_ascii_whitespace() {
+                return Ok(Some(byte));
+            }
+            self.bump()?;
+        }
+        Ok(None)
+    }
+
+    // Copy one object, from its `{` to the matching `}`, into `self.element`
+    fn read_object(&mut self) -> Result<(), Error> {
+        self.element.clear();
+        let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+        while let Some(byte) = self.bump()? {
+            self.element.push(byte);
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(Error::malformed(self.line, "unexpected end of file"))
+    }
+
+    fn next_record(&mut self) -> Result<Option<User>, Error> {
+        loop {
+            let Some(byte) = self.skip_whitespace()? else {
+                let reason = if self.started {
+                    "unexpected end of file, expected `]`"
+                } else {
+                    "empty file, expected a JSON array"
+                };
+                return Err(Error::malformed(self.line, reason));
+            };
+
+            match byte {
+                b'[' if !self.started => self.started = true,
+                _ if !self.started => {
+                    return Err(Error::malformed(self.line, "expected `[`"));
+                }
+                b']' if self.expect_element => {
+                    return Err(Error::malformed(self.line, "expected an object after `,`"));
+                }
+                b']' => return Ok(None),
+                b',' if self.expect_separator => {
+                    self.expect_separator = false;
+                    self.expect_element = true;
+                }
+                _ if self.expect_separator => {
+                    return Err(Error::malformed(self.line, "expected `,` or `]`"));
+                }
+                b'{' => {
+                    let start = self.line;
+                    self.read_object()?;
+                    self.expect_separator = true;
+                    self.expect_element = false;
+                    return serde_json::from_slice(&self.element)
+                        .map(Some)
+                        .map_err(|e| Error::malformed(start + e.line().saturating_sub(1), e));
+                }
+                _ => return Err(Error::malformed(self.line, "expected an object")),
+            }
+            self.bump()?;
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonArrayRecords<R> {
+    type Item = Result<User, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.next_record();
+        // Stop after the closing bracket or the first error; nothing after it is trustworthy
+        if !matches!(record, Ok(Some(_))) {
+            self.done = true;
+        }
+        record.transpose()
+    }
+}
+
+// Columns a CSV dataset may have; the first five are required
+const CSV_COLUMNS: [&str; 9] = [
+    "id", "name", "email", "age", "active", "padding", "profile", "tags", "blob",
+];
+const CSV_REQUIRED: usize = 5;
+
+// RFC 4180 CSV: a header row naming the columns in any order, then one record
+// per row. Quoted fields may hold commas, doubled quotes and line breaks.
+// Empty optional fields are absent; `profile` and `tags` hold JSON, `blob` hex.
+pub struct CsvRecords<R> {
+    reader: R,
+    line: usize,
+    buf: String,
+    // Position in CSV_COLUMNS of each column in the file, once the header is read
+    columns: Option<Vec<usize>>,
+    done: bool,
+}
+
+impl<R: BufRead> CsvRecords<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            buf: String::new(),
+            columns: None,
+            done: false,
+        }
+    }
+
+    // Fields of the next non-blank row, with the line it starts on
+    fn read_row(&mut self) -> Result<Option<(usize, Vec<String>)>, Error> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let (mut in_quotes, mut quoted) = (false, false);
+        let mut start = self.line + 1;
+
+        loop {
+            self.buf.clear();
+            let read = self
+                .reader
+                .read_line(&mut self.buf)
+                .map_err(|e| Error::malformed(self.line + 1, e))?;
+            if read == 0 {
+                if in_quotes {
+                    return Err(Error::malformed(start, "unterminated quoted field"));
+                }
+                if fields.is_empty() && field.is_empty() && !quoted {
+                    return Ok(None);
+                }
+                fields.push(field);
+                return Ok(Some((start, fields)));
+            }
+            self.line += 1;
+
+            let mut chars = self.buf.chars().peekable();
+            while let Some(c) = chars.next() {
+                if in_quotes {
+                    match c {
+                        '"' if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        '"' => in_quotes = false,
+                        c => field.push(c),
+                    }
+                    continue;
+                }
+                match c {
+                    ',' => {
+                        fields.push(std::mem::take(&mut field));
+                        quoted = false;
+                    }
+                    '\r' | '\n' => break,
+                    _ if quoted => {
+                        return Err(Error::malformed(
+                            self.line,
+                            "expected `,` after a quoted field",
+                        ));
+                    }
+                    '"' if field.is_empty() => (in_quotes, quoted) = (true, true),
+                    '"' => return Err(Error::malformed(self.line, "unexpected `\"`")),
+                    c => field.push(c),
+                }
+            }
+
+            if in_quotes {
+                continue;
+            }
+            if fields.is_empty() && field.is_empty() && !quoted {
+                // Blank line
+                start = self.line + 1;
+                continue;
+            }
+            fields.push(field);
+            return Ok(Some((start, fields)));
+        }
+    }
+
+    fn read_header(&mut self) -> Result<Vec<usize>, Error> {
+        let Some((line, names)) = self.read_row()? else {
+            return Err(Error::malformed(1, "empty file, expected a header row"));
+        };
+        let mut columns = Vec::with_capacity(names.len());
+        for (position, name) in names.iter().enumerate() {
+            let name = name.trim();
+            // Named by position only: the header may be the first line of any
+            // file the client pointed at, so it is never echoed back
+            let Some(column) = CSV_COLUMNS.iter().position(|&known| known == name) else {
+                return Err(Error::malformed(
+                    line,
+                    format!(
+                        "unknown column {}, expected one of {}",
+                        position + 1,
+                        CSV_COLUMNS.join(", ")
+                    ),
+                ));
+            };
+            if columns.contains(&column) {
+                return Err(Error::malformed(
+                    line,
+                    format!("duplicate column `{}`", name),
+                ));
+            }
+            columns.push(column);
+        }
+        for (column, name) in CSV_COLUMNS.iter().enumerate().take(CSV_REQUIRED) {
+            if !columns.contains(&column) {
+                return Err(Error::malformed(line, format!("missing column `{}`", name)));
+            }
+        }
+        Ok(columns)
+    }
+
+    fn next_record(&mut self) -> Result<Option<User>, Error> {
+        let columns = match self.columns.take() {
+            Some(columns) => columns,
+            None => self.read_header()?,
+        };
+        let row = self.read_row();
+        let columns = self.columns.insert(columns);
+        let Some((line, fields)) = row? else {
+            return Ok(None);
+        };
+        if fields.len() != columns.len() {
+            return Err(Error::malformed(
+                line,
+                format!("expected {} fields, found {}", columns.len(), fields.len()),
+            ));
+        }
+
+        let mut values: [Option<String>; CSV_COLUMNS.len()] = Default::default();
+        for (&column, field) in columns.iter().zip(fields) {
+            values[column] = Some(field);
+        }
+        let [id, name, email, age, active, padding, profile, tags, blob] = values;
+        let invalid = |column: &str, e: &dyn std::fmt::Display| {
+            Error::malformed(line, format!("{}: {}", column, e))
+        };
+        let optional = |value: Option<String>| value.filter(|value| !value.is_empty());
+
+        Ok(Some(User {
+            id: id.unwrap_or_default(),
+            name: name.unwrap_or_default(),
+            email: email.unwrap_or_default(),
+            age: age
+                .unwrap_or_default()
+                .trim()
+                .parse()
+                .map_err(|e| invalid("age", &e))?,
+            active: parse_bool(active.as_deref().unwrap_or_default())
+                .ok_or_else(|| invalid("active", &"expected true or false"))?,
+            padding: optional(padding),
+            profile: optional(profile)
+                .map(|json| serde_json::from_str(&json))
+                .transpose()
+                .map_err(|e| invalid("profile", &e))?,
+            tags: optional(tags)
+                .map(|json| serde_json::from_str(&json))
+                .transpose()
+                .map_err(|e| invalid("tags", &e))?,
+            blob: optional(blob)
+                .map(|hex| parse_hex(&hex))
+                .transpose()
+                .map_err(|e| invalid("blob", &e))?,
+        }))
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "t" | "1" => Some(true),
+        "false" | "f" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<Vec<u8>, &'static str> {
+    let hex = hex.trim().as_bytes();
+    if !hex.len().is_multiple_of(2) {
+        return Err("odd number of hex digits");
+    }
+    let digit = |byte: u8| (byte as char).to_digit(16).ok_or("invalid hex digit");
+    hex.chunks(2)
+        .map(|pair| Ok((digit(pair[0])? * 16 + digit(pair[1])?) as u8))
+        .collect()
+}
+
+impl<R: BufRead> Iterator for CsvRecords<R> {
+    type Item = Result<User, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.next_record();
+        // A malformed header leaves nothing to read the rows against
+        if matches!(record, Ok(None)) || (record.is_err() && self.columns.is_none()) {
+            self.done = true;
+        }
+        record.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_of(result: Option<Result<User, Error>>) -> usize {
+        match result {
+            Some(Err(Error::Malformed { line, .. })) => line,
+            other => panic!("expected a malformed record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ndjson_reports_the_bad_line() {
+        let input = concat!(
+            r#"{"id":"a","name":"A","email":"a@example.com","age":30,"active":true}"#,
+            "\n\n",
+            r#"{"id":"b","name":"B","email":"b@example.com","age":"old","active":true}"#,
+            "\n",
+        );
+        let mut records = NdjsonRecords::new(input.as_bytes());
+
+        assert_eq!(records.next().unwrap().unwrap().id, "a");
+        assert_eq!(line_of(records.next()), 3);
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn json_array_is_read_element_by_element() {
+        let input = concat!(
+            "[\n",
+            r#"  {"id":"a","name":"A [x]","email":"a@example.com","age":30,"active":true},"#,
+            "\n",
+            r#"  {"id":"b","name":"B \"}\"","email":"b@example.com","age":31,"active":false}"#,
+            "\n]\n",
+        );
+        let users: Vec<User> = JsonArrayRecords::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[1].name, "B \"}\"");
+
+        let missing_comma = concat!(
+            "[\n",
+            r#"{"id":"a","name":"A","email":"a@example.com","age":30,"active":true}"#,
+            "\n",
+            r#"{"id":"b","name":"B","email":"b@example.com","age":31,"active":false}"#,
+            "\n]",
+        );
+        let mut records = JsonArrayRecords::new(missing_comma.as_bytes());
+        assert!(records.next().unwrap().is_ok());
+        assert_eq!(line_of(records.next()), 3);
+        assert!(records.next().is_none());
+
+        let trailing_comma = concat!(
+            "[\n",
+            r#"{"id":"a","name":"A","email":"a@example.com","age":30,"active":true},"#,
+            "\n]",
+        );
+        let mut records = JsonArrayRecords::new(trailing_comma.as_bytes());
+        assert!(records.next().unwrap().is_ok());
+        assert_eq!(line_of(records.next()), 3);
+        assert!(records.next().is_none());
+
+        let empty: Vec<_> = JsonArrayRecords::new(" [ ] ".as_bytes()).collect();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn read_errors_are_reported_as_such() {
+        struct Failing;
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("device unplugged"))
+            }
+        }
+
+        let mut records = JsonArrayRecords::new(std::io::BufReader::new(Failing));
+        match records.next() {
+            Some(Err(Error::Malformed { line, reason })) => {
+                assert_eq!(line, 1);
+                assert!(reason.contains("device unplugged"), "{}", reason);
+            }
+            other => panic!("expected a read error, got {:?}", other),
+        }
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn csv_handles_quoting_and_reports_the_bad_row() {
+        let input = concat!(
+            "age,id,name,email,active,tags\r\n",
+            "30,a,\"Doe, \"\"Jane\"\"\",a@example.com,true,\"[\"\"x\"\"]\"\r\n",
+            "31,b,\"Multi\nLine\",b@example.com,f,\r\n",
+            "\n",
+            "old,c,C,c@example.com,true,\n",
+            "32,d,D,d@example.com,true\n",
+        );
+        let mut records = CsvRecords::new(input.as_bytes());
+
+        let first = records.next().unwrap().unwrap();
+        assert_eq!(first.name, "Doe, \"Jane\"");
+        assert_eq!(first.tags, Some(vec!["x".to_string()]));
+        let second = records.next().unwrap().unwrap();
+        assert_eq!(second.name, "Multi\nLine");
+        assert!(!second.active && second.tags.is_none());
+        assert_eq!(line_of(records.next()), 6);
+        assert_eq!(line_of(records.next()), 7);
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn unknown_csv_column_is_reported_without_its_name() {
+        let input = "id,root:x:0:0:root:/root:/bin/bash\n";
+        let mut records = CsvRecords::new(input.as_bytes());
+
+        let Some(Err(Error::Malformed { line, reason })) = records.next() else {
+            panic!("expected a malformed header");
+        };
+        assert_eq!(line, 1);
+        assert_eq!(
+            reason,
+            format!(
+                "unknown column 2, expected one of {}",
+                CSV_COLUMNS.join(", ")
+            )
+        );
+        assert!(records.next().is_none());
+    }
+}
@@ -1,4 +1,15 @@
+pub mod backend_registry;
+pub mod distribution;
 pub mod error;
+pub mod generator;
+pub mod latency;
+pub mod loader;
+pub mod pacing;
+pub mod payload;
+pub mod runs;
 pub mod shared_state;
+pub mod statistics;
 pub mod user_struct;
 pub mod users;
+pub mod verification;
+pub mod ycsb;
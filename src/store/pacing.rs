@@ -0,0 +1,136 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use futures::{StreamExt, stream::FuturesUnordered};
+
+use crate::store::error::Error;
+
+// When the operations of a phase are issued. Closed loop issues each one as
+// soon as fewer than `concurrency` are in flight; open loop issues them at a
+// fixed rate, so a slow response delays nothing but its own completion.
+struct Schedule {
+    start: Instant,
+    rate: Option<f64>,
+    issued: u64,
+}
+
+impl Schedule {
+    fn new(rate: Option<f64>) -> Self {
+        Self {
+            start: Instant::now(),
+            rate,
+            issued: 0,
+        }
+    }
+
+    /// Intended start of the next operation; `None` in closed loop, where an
+    /// operation starts whenever it is issued
+    fn next_due(&mut self) -> Result<Option<Instant>, Error> {
+        let Some(rate) = self.rate else {
+            return Ok(None);
+        };
+        let due = Duration::try_from_secs_f64(self.issued as f64 / rate)
+            .ok()
+            .and_then(|offset| self.start.checked_add(offset))
+            .ok_or(Error::Schedule {
+                issued: self.issued,
+                rate,
+            })?;
+        self.issued += 1;
+        Ok(Some(due))
+    }
+}
+
+/// Next completion to record before the operation due at `due` may be issued,
+/// or `None` once it may: a slot is free and, in open loop, its time has come
+async fn until_due<F: Future>(
+    in_flight: &mut FuturesUnordered<F>,
+    due: Option<Instant>,
+    concurrency: usize,
+) -> Option<F::Output> {
+    if in_flight.len() >= concurrency {
+        return in_flight.next().await;
+    }
+    let due = due?;
+    // Tokio timers fire on a millisecond tick, so an operation may be issued
+    // up to a millisecond late; its latency still counts from `due`
+    let sleep = tokio::time::sleep_until(due.into());
+    if in_flight.is_empty() {
+        sleep.await;
+        return None;
+    }
+    tokio::select! {
+        biased;
+        _ = sleep => None,
+        outcome = in_flight.next() => outcome,
+    }
+}
+
+/// Await `operation`, returning its output with the time from `due` (or from
+/// now, when there is no intended start) to its completion. Measuring from the
+/// intended start counts the queueing delay a closed loop would hide.
+async fn timed_since<F: Future>(due: Option<Instant>, operation: F) -> (F::Output, Duration) {
+    let start = due.unwrap_or_else(Instant::now);
+    let output = operation.await;
+    (output, start.elapsed())
+}
+
+/// Issue one operation per item, closed loop with up to `concurrency` in flight
+/// or open loop at `rate`, handing each output and its latency to `complete`.
+/// Returns how long the phase took; an error from `complete` ends it early.
+pub async fn run_phase<T, F>(
+    items: impl IntoIterator<Item = T>,
+    rate: Option<f64>,
+    concurrency: usize,
+    mut op: impl FnMut(T) -> F,
+    mut complete: impl FnMut(F::Output, Duration) -> Result<(), Error>,
+) -> Result<Duration, Error>
+where
+    F: Future,
+{
+    let start = Instant::now();
+    let mut schedule = Schedule::new(rate);
+    let mut in_flight = FuturesUnordered::new();
+    for item in items {
+        let due = schedule.next_due()?;
+        while let Some((output, latency)) = until_due(&mut in_flight, due, concurrency).await {
+            complete(output, latency)?;
+        }
+        in_flight.push(timed_since(due, op(item)));
+    }
+    while let Some((output, latency)) = in_flight.next().await {
+        complete(output, latency)?;
+    }
+    Ok(start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_loop_spaces_operations_at_the_target_rate() {
+        let mut closed = Schedule::new(None);
+        assert!(closed.next_due().unwrap().is_none());
+
+        let mut open = Schedule::new(Some(1_000.0));
+        let mut due = || open.next_due().unwrap().unwrap();
+        let first = due();
+        let second = due();
+        let eleventh = (0..9).map(|_| due()).last().unwrap();
+        assert_eq!(second - first, Duration::from_millis(1));
+        assert_eq!(eleventh - first, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn unrepresentable_due_time_is_an_error() {
+        let mut open = Schedule::new(Some(1e-300));
+        assert!(open.next_due().unwrap().is_some());
+        assert!(matches!(
+            open.next_due(),
+            Err(Error::Schedule { issued: 1, .. })
+        ));
+    }
+}
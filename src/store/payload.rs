@@ -0,0 +1,62 @@
+use rand::{Rng, distributions::Alphanumeric};
+
+use crate::{
+    config::PayloadParams,
+    store::user_struct::{Address, Profile, User},
+};
+
+const STREETS: [&str; 6] = ["Main", "Oak", "Maple", "Cedar", "Elm", "Park"];
+const CITIES: [&str; 6] = [
+    "Springfield",
+    "Riverside",
+    "Lisbon",
+    "Osaka",
+    "Nairobi",
+    "Oslo",
+];
+const COUNTRIES: [&str; 6] = ["US", "GB", "PT", "JP", "KE", "NO"];
+// Tags are `tag-` and four hex digits
+const TAG_BYTES: usize = 8;
+
+fn text(rng: &mut impl Rng, len: usize) -> String {
+    rng.sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn profile(rng: &mut impl Rng, len: usize) -> Profile {
+    let address = Address {
+        street: format!(
+            "{} {} Street",
+            rng.gen_range(1..1_000),
+            STREETS[rng.gen_range(0..STREETS.len())]
+        ),
+        city: CITIES[rng.gen_range(0..CITIES.len())].to_string(),
+        country: COUNTRIES[rng.gen_range(0..COUNTRIES.len())].to_string(),
+    };
+    let address_len = address.street.len() + address.city.len() + address.country.len();
+    Profile {
+        bio: text(rng, len.saturating_sub(address_len)),
+        address,
+    }
+}
+
+fn tags(rng: &mut impl Rng, len: usize) -> Vec<String> {
+    (0..(len / TAG_BYTES).max(1))
+        .map(|_| format!("tag-{:04x}", rng.gen::<u16>()))
+        .collect()
+}
+
+/// Fill the payload fields `params` enables, splitting `params.size` between them
+pub fn fill(user: &mut User, params: &PayloadParams, rng: &mut impl Rng) {
+    let share = params.size / params.fields().max(1);
+    user.padding = params.padding.then(|| text(rng, share));
+    user.profile = params.nested.then(|| profile(rng, share));
+    user.tags = params.tags.then(|| tags(rng, share));
+    user.blob = params.blob.then(|| {
+        let mut blob = vec![0u8; share];
+        rng.fill(&mut blob[..]);
+        blob
+    });
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+// Finished runs kept for polling; beyond this the oldest are forgotten
+const MAX_FINISHED_RUNS: usize = 100;
+// Runs queued or running at once; POST /runs is turned away beyond this
+pub const MAX_PENDING_RUNS: usize = 32;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum RunStatus {
+    // Waiting for the runs ahead of it
+    Queued,
+    Running {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        backend: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        workload: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        concurrency: Option<usize>,
+        phase: &'static str,
+    },
+    // Finished; backends that failed are marked in their own results
+    Done,
+    // Could not start, e.g. an unknown backend or an unreadable dataset
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Run {
+    pub id: String,
+    #[serde(flatten)]
+    pub status: RunStatus,
+    // Per-backend responses, as POST /benchmark returns them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<serde_json::Value>,
+}
+
+#[derive(Default)]
+struct RunTable {
+    runs: HashMap<String, Run>,
+    // Finished run ids, oldest first
+    finished: VecDeque<String>,
+}
+
+// Benchmark runs submitted through POST /runs, by id
+#[derive(Clone, Default)]
+pub struct Runs {
+    table: Arc<Mutex<RunTable>>,
+}
+
+impl Runs {
+    fn table(&self) -> MutexGuard<'_, RunTable> {
+        // Updates are single assignments, so a poisoned table is still consistent
+        self.table.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Register a new queued run, or `None` when `MAX_PENDING_RUNS` are
+    /// already queued or running
+    pub fn create(&self) -> Option<Run> {
+        let mut table = self.table();
+        if table.runs.len() - table.finished.len() >= MAX_PENDING_RUNS {
+            return None;
+        }
+        let run = Run {
+            id: Uuid::new_v4().to_string(),
+            status: RunStatus::Queued,
+            results: None,
+        };
+        table.runs.insert(run.id.clone(), run.clone());
+        Some(run)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Run> {
+        self.table().runs.get(id).cloned()
+    }
+
+    pub fn set_status(&self, id: &str, status: RunStatus) {
+        if let Some(run) = self.table().runs.get_mut(id) {
+            run.status = status;
+        }
+    }
+
+    /// Record the outcome of a run, forgetting the oldest finished runs
+    pub fn finish(&self, id: &str, outcome: Result<serde_json::Value, String>) {
+        let mut table = self.table();
+        let Some(run) = table.runs.get_mut(id) else {
+            return;
+        };
+        match outcome {
+            Ok(results) => {
+                run.status = RunStatus::Done;
+                run.results = Some(results);
+            }
+            Err(error) => run.status = RunStatus::Failed { error },
+        }
+
+        table.finished.push_back(id.to_string());
+        while table.finished.len() > MAX_FINISHED_RUNS {
+            if let Some(oldest) = table.finished.pop_front() {
+                table.runs.remove(&oldest);
+            }
+        }
+    }
+}
+
+// Where a benchmark reports what it is doing; a no-op outside POST /runs
+#[derive(Clone, Default)]
+pub struct Progress {
+    run: Option<(Runs, String)>,
+    backend: Option<String>,
+    workload: Option<String>,
+    concurrency: Option<usize>,
+}
+
+impl Progress {
+    pub fn for_run(runs: Runs, id: String) -> Self {
+        Self {
+            run: Some((runs, id)),
+            ..Self::default()
+        }
+    }
+
+    /// The same run, now working on `workload` against `backend`
+    pub fn at(&self, backend: &str, workload: &str, concurrency: usize) -> Self {
+        Self {
+            run: self.run.clone(),
+            backend: Some(backend.to_string()),
+            workload: Some(workload.to_string()),
+            concurrency: Some(concurrency),
+        }
+    }
+
+    pub fn phase(&self, phase: &'static str) {
+        if let Some((runs, id)) = &self.run {
+            runs.set_status(
+                id,
+                RunStatus::Running {
+                    backend: self.backend.clone(),
+                    workload: self.workload.clone(),
+                    concurrency: self.concurrency,
+                    phase,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_runs_are_kept_up_to_the_limit() {
+        let runs = Runs::default();
+        let first = runs.create().unwrap().id;
+        let progress = Progress::for_run(runs.clone(), first.clone()).at("RocksDB", "update", 4);
+        progress.phase("update");
+        assert!(matches!(
+            runs.get(&first).unwrap().status,
+            RunStatus::Running {
+                phase: "update",
+                ..
+            }
+        ));
+
+        runs.finish(&first, Err("no such backend".to_string()));
+        assert!(matches!(
+            runs.get(&first).unwrap().status,
+            RunStatus::Failed { .. }
+        ));
+
+        let queued = runs.create().unwrap().id;
+        for _ in 0..MAX_FINISHED_RUNS {
+            let id = runs.create().unwrap().id;
+            runs.finish(&id, Ok(serde_json::Value::Array(Vec::new())));
+        }
+        assert!(runs.get(&first).is_none());
+        assert!(matches!(
+            runs.get(&queued).unwrap().status,
+            RunStatus::Queued
+        ));
+    }
+
+    #[test]
+    fn pending_runs_are_capped() {
+        let runs = Runs::default();
+        let ids: Vec<String> = (0..MAX_PENDING_RUNS)
+            .map(|_| runs.create().unwrap().id)
+            .collect();
+        assert!(runs.create().is_none());
+
+        runs.finish(&ids[0], Ok(serde_json::Value::Null));
+        assert!(runs.create().is_some());
+        assert!(runs.create().is_none());
+    }
+}
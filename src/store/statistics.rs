@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::store::users::BenchmarkResult;
+
+// Two-sided 95% Student t critical values for 1..=30 degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+// Beyond the table, the value at the bottom of each range, which is the
+// largest in it, so the interval is never narrower than it should be
+fn t_critical_95(degrees_of_freedom: usize) -> f64 {
+    match degrees_of_freedom {
+        0 => f64::NAN,
+        1..=30 => T_95[degrees_of_freedom - 1],
+        31..=40 => 2.042,
+        41..=60 => 2.021,
+        61..=120 => 2.000,
+        121.. => 1.980,
+    }
+}
+
+// Spread of one metric across the measured repetitions
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricStats {
+    pub mean: f64,
+    // Sample standard deviation (n - 1)
+    pub stddev: f64,
+    // stddev / mean; absent when the mean is zero
+    pub cv: Option<f64>,
+    // 95% confidence interval of the mean; absent for a single repetition
+    pub ci95_low: Option<f64>,
+    pub ci95_high: Option<f64>,
+}
+
+impl MetricStats {
+    pub fn from_samples(samples: &[f64]) -> Self {
+        let n = samples.len();
+        let mean = if n > 0 {
+            samples.iter().sum::<f64>() / n as f64
+        } else {
+            0.0
+        };
+        let stddev = if n > 1 {
+            let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+            variance.sqrt()
+        } else {
+            0.0
+        };
+        let half_width = (n > 1).then(|| t_critical_95(n - 1) * stddev / (n as f64).sqrt());
+
+        Self {
+            mean,
+            stddev,
+            cv: (mean != 0.0).then(|| stddev / mean),
+            ci95_low: half_width.map(|h| mean - h),
+            ci95_high: half_width.map(|h| mean + h),
+        }
+    }
+}
+
+/// Statistics for every metric reported by the runs, keyed by metric name
+pub fn summarize_runs(runs: &[BenchmarkResult]) -> BTreeMap<String, MetricStats> {
+    let mut samples: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for run in runs {
+        for (name, value) in run.metrics() {
+            samples.entry(name).or_default().push(value);
+        }
+    }
+
+    samples
+        .into_iter()
+        .map(|(name, values)| (name, MetricStats::from_samples(&values)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn samples_give_mean_spread_and_interval() {
+        // Mean 5, sample variance 32 / 7
+        let stats = MetricStats::from_samples(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        let stddev = (32.0f64 / 7.0).sqrt();
+        let half_width = 2.365 * stddev / 8f64.sqrt();
+
+        assert_close(stats.mean, 5.0);
+        assert_close(stats.stddev, 2.138);
+        assert_close(stats.cv.unwrap(), stddev / 5.0);
+        assert_close(stats.ci95_low.unwrap(), 5.0 - half_width);
+        assert_close(stats.ci95_high.unwrap(), 5.0 + half_width);
+    }
+
+    #[test]
+    fn single_sample_has_no_interval() {
+        let stats = MetricStats::from_samples(&[3.0]);
+        assert_close(stats.mean, 3.0);
+        assert_close(stats.stddev, 0.0);
+        assert!(stats.ci95_low.is_none() && stats.ci95_high.is_none());
+    }
+
+    #[test]
+    fn critical_values_never_narrow_the_interval() {
+        for df in 2..200 {
+            assert!(t_critical_95(df) <= t_critical_95(df - 1), "df {}", df);
+        }
+        assert_close(t_critical_95(31), 2.042);
+        assert_close(t_critical_95(121), 1.980);
+    }
+}
@@ -7,4 +7,56 @@ pub struct User {
     pub email: String,
     pub age: i32,
     pub active: bool,
+    // Optional payload fields, filled when a benchmark asks for larger records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub padding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(json(nullable))]
+    pub profile: Option<Profile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(json(nullable))]
+    pub tags: Option<Vec<String>>,
+    // Stored as native binary: BSON in MongoDB and the key-value engines,
+    // SurrealDB bytes, BYTEA in PostgreSQL
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "serde_bytes")]
+    pub blob: Option<Vec<u8>>,
+}
+
+// Nested payload object
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Profile {
+    pub bio: String,
+    pub address: Address,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Address {
+    pub street: String,
+    pub city: String,
+    pub country: String,
+}
+
+// Fields rewritten by the update workload
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserUpdate {
+    pub email: String,
+    pub age: i32,
+    pub active: bool,
+}
+
+impl UserUpdate {
+    /// Flip `active`, bump `age` and rewrite `email` relative to `user`
+    pub fn for_user(user: &User) -> Self {
+        UserUpdate {
+            email: format!("updated.{}", user.email),
+            age: user.age.wrapping_add(1),
+            active: !user.active,
+        }
+    }
+
+    pub fn apply(&self, user: &mut User) {
+        user.email = self.email.clone();
+        user.age = self.age;
+        user.active = self.active;
+    }
 }
@@ -1,19 +1,33 @@
-use std::{fs::File, io::Read, sync::Arc, time::Instant};
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use leveldb::database::Database as LevelDB;
-use mongodb::Database;
-use rocksdb::DB as RocksDB;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
 use serde::Serialize;
-use sqlx::PgPool;
-use std::sync::Mutex;
-use surrealdb::{Surreal, engine::remote::ws};
 
 use crate::{
-    store::{error, user_struct::User},
-    utils::db_operations::{
-        level_db_operations::LevelOperations, mongo_db_operations::MongoOperations,
-        psql_db_operations::PgOperations, rocks_db_operations::RocksOperations,
-        surreal_db_operations::SurrealOperations,
+    config::{
+        BenchmarkConfig, FileFormat, GeneratorParams, KeyDistribution, PayloadParams, Workload,
+    },
+    store::{
+        distribution::{KeyChooser, rng},
+        error,
+        generator::UserGenerator,
+        latency::{LatencyHistogram, PhaseSummary},
+        loader,
+        pacing::run_phase,
+        payload,
+        runs::Progress,
+        user_struct::{User, UserUpdate},
+        verification::{VerificationReport, verify_users},
+        ycsb::{self, Mix, YcsbReport},
+    },
+    utils::db_operations::storage_backend::{
+        BoundaryStats, CopyFormat, SharedUsers, StorageBackend,
     },
 };
 
@@ -22,135 +36,582 @@ pub struct BenchmarkResult {
     pub insert_time_s: f64,
     pub read_time_s: f64,
     pub clear_time_s: f64,
+    // Record counts as reported by the backend, not as requested
+    pub inserted: usize,
+    pub read_back: usize,
+    pub cleared: usize,
+    // Rate operations were issued at in open loop; each phase's `ops_per_sec`
+    // is what was achieved, and its latencies count from intended start times
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_ops_per_sec: Option<f64>,
+    // Per-operation latency and throughput of each phase
+    pub insert: PhaseSummary,
+    pub read: PhaseSummary,
+    pub clear: PhaseSummary,
+    // Primary-key reads of point_lookup, and how many of them found a record
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookup: Option<PhaseSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub found: Option<usize>,
+    // In-place updates of the update workload, and how many hit an existing record
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update: Option<PhaseSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<usize>,
+    // Single-record deletes of the delete workload; `cleared` then counts
+    // only what was left over
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete: Option<PhaseSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<usize>,
+    // Secondary index build and range queries of filtered_query; the rows
+    // returned are checked against a count made from the loaded records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_build_time_s: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<PhaseSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_rows: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_query_rows: Option<usize>,
+    // YCSB operation mix; its inserts add to what `cleared` should count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ycsb: Option<YcsbReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<VerificationReport>,
+    // Cost of handing calls to the blocking pool, for embedded engines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocking_boundary: Option<BoundaryReport>,
+}
+
+// Async/blocking hand-off cost over one run, already included in the phase timings
+#[derive(Debug, Serialize)]
+pub struct BoundaryReport {
+    pub calls: u64,
+    pub overhead_s: f64,
+    pub mean_overhead_us: f64,
+}
+
+impl From<BoundaryStats> for BoundaryReport {
+    fn from(stats: BoundaryStats) -> Self {
+        Self {
+            calls: stats.calls,
+            overhead_s: stats.overhead.as_secs_f64(),
+            mean_overhead_us: if stats.calls > 0 {
+                stats.overhead.as_secs_f64() * 1_000_000.0 / stats.calls as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+impl BenchmarkResult {
+    /// Describe why the run should count as failed: counts that disagree with
+    /// `expected`, or a verification that found bad records
+    pub fn failure(&self, expected: usize) -> Option<String> {
+        if self.inserted != expected {
+            Some(format!(
+                "inserted {} of {} records",
+                self.inserted, expected
+            ))
+        } else if self.read_back != self.inserted {
+            Some(format!(
+                "read back {} of {} inserted records",
+                self.read_back, self.inserted
+            ))
+        } else if self.cleared != self.remaining() {
+            Some(format!(
+                "cleared {} of {} remaining records",
+                self.cleared,
+                self.remaining()
+            ))
+        } else {
+            shortfall(&self.lookup, self.found, "found")
+                .or_else(|| shortfall(&self.update, self.updated, "updated"))
+                .or_else(|| shortfall(&self.delete, self.deleted, "deleted"))
+                .or_else(|| {
+                    let (rows, expected) = self.query_rows.zip(self.expected_query_rows)?;
+                    (rows != expected).then(|| {
+                        format!("queries returned {} of {} matching records", rows, expected)
+                    })
+                })
+                .or_else(|| {
+                    let misses = self.ycsb.as_ref()?.misses;
+                    (misses > 0).then(|| format!("{} YCSB operations missed their record", misses))
+                })
+                .or_else(|| {
+                    self.verification
+                        .as_ref()
+                        .filter(|report| !report.is_clean())
+                        .map(VerificationReport::summary)
+                })
+        }
+    }
+
+    // Records that should still be stored before the final clear
+    fn remaining(&self) -> usize {
+        let added = self.ycsb.as_ref().map_or(0, |report| report.inserted);
+        (self.inserted + added).saturating_sub(self.deleted.unwrap_or(0))
+    }
+
+    /// Timing metrics by name, e.g. `insert_time_s` or `read.p99_us`
+    pub fn metrics(&self) -> Vec<(String, f64)> {
+        let mut metrics = vec![
+            ("insert_time_s".to_string(), self.insert_time_s),
+            ("read_time_s".to_string(), self.read_time_s),
+            ("clear_time_s".to_string(), self.clear_time_s),
+        ];
+        if let Some(index_build_time_s) = self.index_build_time_s {
+            metrics.push(("index_build_time_s".to_string(), index_build_time_s));
+        }
+        for (phase, summary) in [
+            ("insert", Some(&self.insert)),
+            ("read", Some(&self.read)),
+            ("clear", Some(&self.clear)),
+            ("lookup", self.lookup.as_ref()),
+            ("update", self.update.as_ref()),
+            ("delete", self.delete.as_ref()),
+            ("query", self.query.as_ref()),
+        ] {
+            let Some(summary) = summary else {
+                continue;
+            };
+            metrics.extend(
+                summary
+                    .metrics()
+                    .into_iter()
+                    .map(|(name, value)| (format!("{}.{}", phase, name), value)),
+            );
+        }
+        if let Some(report) = &self.ycsb {
+            metrics.push(("ycsb.runtime_s".to_string(), report.runtime_s));
+            metrics.push(("ycsb.ops_per_sec".to_string(), report.ops_per_sec));
+            for (op, summary) in &report.ops {
+                metrics.extend(
+                    summary
+                        .metrics()
+                        .into_iter()
+                        .map(|(name, value)| (format!("ycsb.{}.{}", op, name), value)),
+                );
+            }
+        }
+        if let Some(boundary) = &self.blocking_boundary {
+            metrics.push((
+                "blocking_boundary.overhead_s".to_string(),
+                boundary.overhead_s,
+            ));
+            metrics.push((
+                "blocking_boundary.mean_overhead_us".to_string(),
+                boundary.mean_overhead_us,
+            ));
+        }
+        metrics
+    }
+}
+
+// Describe a per-record phase where fewer than all operations hit a record
+fn shortfall(phase: &Option<PhaseSummary>, hits: Option<usize>, verb: &str) -> Option<String> {
+    let (phase, hits) = phase.as_ref().zip(hits)?;
+    (hits as u64 != phase.ops).then(|| format!("{} {} of {} records", verb, hits, phase.ops))
+}
+
+// How the insert phase hands records to the backend
+#[derive(Clone, Copy)]
+enum InsertMode {
+    Single,
+    Batch,
+    Bulk(CopyFormat),
+}
+
+async fn insert_chunk(
+    backend: &dyn StorageBackend,
+    users: SharedUsers,
+    mode: InsertMode,
+    batch_size: usize,
+) -> Result<usize, error::Error> {
+    match mode {
+        InsertMode::Single => {
+            for user in users.records() {
+                backend.insert_user(user).await?;
+            }
+            Ok(users.len())
+        }
+        InsertMode::Batch => backend.insert_users(users).await,
+        InsertMode::Bulk(format) => backend.bulk_load(users, format, batch_size).await,
+    }
+}
+
+/// Await `operation`, returning its output with how long it took
+pub async fn timed<F: Future>(operation: F) -> (F::Output, Duration) {
+    let start = Instant::now();
+    let output = operation.await;
+    (output, start.elapsed())
 }
 
 // Wrapper struct for Vec<User> with benchmark methods
 pub struct Users {
-    data: Vec<User>,
+    data: Arc<[User]>,
 }
 
 impl Users {
-    /// Load users from a JSON file
-    pub fn load(filepath: &str) -> Result<Self, error::Error> {
-        let mut file = File::open(filepath).map_err(error::Error::IoError)?;
+    /// Load up to `count` users from a dataset file, streaming it record by
+    /// record. With a seed, a seeded uniform sample of the whole file (reservoir
+    /// sampling) in shuffled order; otherwise the first `count` records.
+    pub fn load(
+        path: &Path,
+        format: FileFormat,
+        count: usize,
+        seed: Option<u64>,
+    ) -> Result<Self, error::Error> {
+        let records = loader::records(path, format)?;
+        let mut data = Vec::new();
 
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
-            .map_err(error::Error::IoError)?;
+        match seed {
+            None => {
+                for record in records.take(count) {
+                    data.push(record?);
+                }
+            }
+            Some(seed) => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                for (seen, record) in records.enumerate() {
+                    let user = record?;
+                    if seen < count {
+                        data.push(user);
+                    } else {
+                        let slot = rng.gen_range(0..=seen);
+                        if slot < count {
+                            data[slot] = user;
+                        }
+                    }
+                }
+                data.shuffle(&mut rng);
+            }
+        }
 
-        let data: Vec<User> = serde_json::from_str(&contents).map_err(error::Error::SerdeError)?;
+        Ok(Users { data: data.into() })
+    }
 
-        Ok(Users { data })
+    /// Generate `count` records, seeded when a seed is given
+    pub fn generate(count: usize, params: GeneratorParams, seed: Option<u64>) -> Self {
+        Users {
+            data: UserGenerator::new(params, seed).take(count).collect(),
+        }
     }
 
-    /// Benchmark PostgreSQL database operations
-    pub async fn postgres_benchmark(&self, pool: &PgPool) -> Result<BenchmarkResult, error::Error> {
-        // Clear existing data
-        let start_clear = Instant::now();
-        pool.clear_users().await?;
-        let clear_time_s = start_clear.elapsed().as_secs_f64();
-
-        // Insert all users
-        let start_write = Instant::now();
-        for user in &self.data {
-            pool.insert_users(user).await?;
+    /// Fill the payload fields of every record, seeded when a seed is given
+    pub fn with_payload(mut self, params: &PayloadParams, seed: Option<u64>) -> Self {
+        let mut rng = rng(seed);
+        for user in Arc::make_mut(&mut self.data) {
+            payload::fill(user, params, &mut rng);
         }
+        self
+    }
 
-        let insert_time_s = start_write.elapsed().as_secs_f64();
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Benchmark a storage backend: insert every user, read them all back, then clear them.
+    /// The store is emptied first (untimed) so leftovers don't skew the counts.
+    /// In batch and bulk workloads each insert latency covers a whole batch or load.
+    pub async fn benchmark(
+        &self,
+        backend: &dyn StorageBackend,
+        config: &BenchmarkConfig,
+        workload: Workload,
+        progress: &Progress,
+    ) -> Result<BenchmarkResult, error::Error> {
+        progress.phase("prepare");
+        backend.clear_users().await?;
+        let boundary_start = backend.boundary_stats();
+
+        let (mode, chunk_size) = match workload {
+            Workload::InsertRead => (InsertMode::Single, 1),
+            Workload::BatchInsertRead => (InsertMode::Batch, config.batch_size),
+            Workload::BulkLoadText => (InsertMode::Bulk(CopyFormat::Text), self.data.len()),
+            Workload::BulkLoadBinary => (InsertMode::Bulk(CopyFormat::Binary), self.data.len()),
+            // The rest measure later phases, so load as fast as batching allows
+            _ => (InsertMode::Batch, config.batch_size),
+        };
+
+        // Insert all users, keeping up to `concurrency` inserts in flight
+        progress.phase("insert");
+        let users = SharedUsers::new(Arc::clone(&self.data));
+        let mut insert_latency = LatencyHistogram::default();
+        let mut inserted = 0;
+        let insert_elapsed = run_phase(
+            users.batches(chunk_size),
+            config.target_rate,
+            config.concurrency,
+            |chunk| insert_chunk(backend, chunk, mode, config.batch_size),
+            |result, latency| {
+                inserted += result?;
+                insert_latency.record(latency);
+                Ok(())
+            },
+        )
+        .await?;
 
         // Read all users
-        let start_read = Instant::now();
-        pool.read_users().await?;
-        let read_time_s = start_read.elapsed().as_secs_f64();
+        progress.phase("read");
+        let mut read_latency = LatencyHistogram::default();
+        let (read_users, read_elapsed) = timed(backend.read_users()).await;
+        let read_users = read_users?;
+        read_latency.record(read_elapsed);
+        let read_back = read_users.len();
+
+        // Optional, untimed comparison against the loaded data
+        let verification = config.verify.then(|| verify_users(&self.data, &read_users));
+
+        // Point reads, for workloads that measure them
+        let (lookup, found) = match workload {
+            Workload::PointLookup => {
+                progress.phase("lookup");
+                let (summary, found) = self.lookup_phase(backend, config).await?;
+                (Some(summary), Some(found))
+            }
+            _ => (None, None),
+        };
+
+        // In-place updates, for workloads that measure them
+        let (update, updated) = match workload {
+            Workload::Update => {
+                progress.phase("update");
+                let (summary, updated) = self.update_phase(backend, config).await?;
+                (Some(summary), Some(updated))
+            }
+            _ => (None, None),
+        };
+
+        // Single-record deletes, for workloads that measure them
+        let (delete, deleted) = match workload {
+            Workload::Delete => {
+                progress.phase("delete");
+                let (summary, deleted) = self.delete_phase(backend, config).await?;
+                (Some(summary), Some(deleted))
+            }
+            _ => (None, None),
+        };
+
+        // Index build and range queries, for workloads that measure them;
+        // the index is dropped again (untimed) so later runs start without it
+        let mut index_build_time_s = None;
+        let (query, query_rows, expected_query_rows) = match workload {
+            Workload::FilteredQuery => {
+                progress.phase("index_build");
+                backend.drop_active_age_index().await?;
+                let (built, build_elapsed) = timed(backend.create_active_age_index()).await;
+                built?;
+                index_build_time_s = Some(build_elapsed.as_secs_f64());
+
+                progress.phase("query");
+                let queried = self.query_phase(backend, config).await;
+                backend.drop_active_age_index().await?;
+                let (summary, rows, expected) = queried?;
+                (Some(summary), Some(rows), Some(expected))
+            }
+            _ => (None, None, None),
+        };
+
+        // YCSB operation mix, for the YCSB workloads
+        let ycsb = match Mix::for_workload(workload) {
+            Some(mix) => {
+                progress.phase("ycsb");
+                Some(ycsb::run(&self.data, backend, config, &mix).await?)
+            }
+            None => None,
+        };
+
+        // Clear what is left; engines whose clear cannot count what it
+        // removes are counted first, outside the timer
+        progress.phase("clear");
+        let stored = backend.count_users().await?;
+        let mut clear_latency = LatencyHistogram::default();
+        let (cleared, clear_elapsed) = timed(backend.clear_users()).await;
+        let cleared = cleared?.or(stored).unwrap_or_default();
+        clear_latency.record(clear_elapsed);
+
+        let blocking_boundary = backend
+            .boundary_stats()
+            .zip(boundary_start)
+            .map(|(end, start)| end.since(start).into());
 
         Ok(BenchmarkResult {
-            insert_time_s,
-            read_time_s,
-            clear_time_s,
+            insert_time_s: insert_elapsed.as_secs_f64(),
+            read_time_s: read_elapsed.as_secs_f64(),
+            clear_time_s: clear_elapsed.as_secs_f64(),
+            inserted,
+            read_back,
+            cleared,
+            target_ops_per_sec: config.target_rate,
+            insert: insert_latency.summary(insert_elapsed),
+            read: read_latency.summary(read_elapsed),
+            clear: clear_latency.summary(clear_elapsed),
+            lookup,
+            found,
+            update,
+            updated,
+            delete,
+            deleted,
+            index_build_time_s,
+            query,
+            query_rows,
+            expected_query_rows,
+            ycsb,
+            verification,
+            blocking_boundary,
         })
     }
 
-    /// Benchmark MongoDB database operations
-    pub async fn mongo_benchmark(&self, db: &Database) -> Result<BenchmarkResult, error::Error> {
-        let start_clear = Instant::now();
-        db.clear_users().await?;
-        let clear_time_s = start_clear.elapsed().as_secs_f64();
-        let start_write = Instant::now();
-        for user in &self.data {
-            db.insert_user(user).await?;
-        }
-        let insert_time_s = start_write.elapsed().as_secs_f64();
-        let start_read = Instant::now();
-        db.read_users().await?;
-        let read_time_s = start_read.elapsed().as_secs_f64();
-        Ok(BenchmarkResult {
-            insert_time_s,
-            read_time_s,
-            clear_time_s,
-        })
+    /// `count` records drawn with replacement from the loaded ones, following
+    /// the configured key distribution (uniform by default)
+    fn sample(&self, count: usize, config: &BenchmarkConfig) -> Vec<&User> {
+        let distribution = config.distribution.unwrap_or(KeyDistribution::Uniform);
+        let mut keys = KeyChooser::new(distribution, self.data.len());
+        let mut rng = rng(config.seed);
+        (0..count)
+            .map(|_| &self.data[keys.next(&mut rng, self.data.len())])
+            .collect()
     }
 
-    /// Benchmark SurrealDB database operations
-    pub async fn surreal_benchmark(
+    // Fetch `config.lookups` random ids by primary key, returning the phase
+    // summary and how many lookups found their record
+    async fn lookup_phase(
         &self,
-        db: &Surreal<ws::Client>,
-    ) -> Result<BenchmarkResult, error::Error> {
-        let start_clear = Instant::now();
-        db.clear_users().await?;
-        let clear_time_s = start_clear.elapsed().as_secs_f64();
-        let start_write = Instant::now();
-        for user in &self.data {
-            db.insert_user(user).await?;
-        }
-        let insert_time_s = start_write.elapsed().as_secs_f64();
-        let start_read = Instant::now();
-        db.read_users().await?;
-        let read_time_s = start_read.elapsed().as_secs_f64();
-        Ok(BenchmarkResult {
-            insert_time_s,
-            read_time_s,
-            clear_time_s,
-        })
+        backend: &dyn StorageBackend,
+        config: &BenchmarkConfig,
+    ) -> Result<(PhaseSummary, usize), error::Error> {
+        let users = self.sample(config.lookups, config);
+
+        let mut latency = LatencyHistogram::default();
+        let mut found = 0;
+        let elapsed = run_phase(
+            users,
+            config.target_rate,
+            config.concurrency,
+            |user| backend.get_user(&user.id),
+            |result, elapsed| {
+                found += result?.is_some() as usize;
+                latency.record(elapsed);
+                Ok(())
+            },
+        )
+        .await?;
+
+        Ok((latency.summary(elapsed), found))
     }
 
-    pub fn rocks_benchmark(&self, db: &Arc<RocksDB>) -> Result<BenchmarkResult, error::Error> {
-        let start_clear = Instant::now();
-        db.clear_users()?;
-        let clear_time_s = start_clear.elapsed().as_secs_f64();
-        let start_write = Instant::now();
-        for user in &self.data {
-            db.insert_user(user)?;
-        }
-        let insert_time_s = start_write.elapsed().as_secs_f64();
-        let start_read = Instant::now();
-        db.read_users()?;
-        let read_time_s = start_read.elapsed().as_secs_f64();
-        Ok(BenchmarkResult {
-            insert_time_s,
-            read_time_s,
-            clear_time_s,
-        })
+    // Apply `config.updates` in-place updates to random records, returning the
+    // phase summary and how many updates hit an existing record
+    async fn update_phase(
+        &self,
+        backend: &dyn StorageBackend,
+        config: &BenchmarkConfig,
+    ) -> Result<(PhaseSummary, usize), error::Error> {
+        let updates: Vec<(&str, UserUpdate)> = self
+            .sample(config.updates, config)
+            .into_iter()
+            .map(|user| (user.id.as_str(), UserUpdate::for_user(user)))
+            .collect();
+
+        let mut latency = LatencyHistogram::default();
+        let mut updated = 0;
+        let elapsed = run_phase(
+            &updates,
+            config.target_rate,
+            config.concurrency,
+            |(id, update)| backend.update_user(id, update),
+            |result, elapsed| {
+                updated += result? as usize;
+                latency.record(elapsed);
+                Ok(())
+            },
+        )
+        .await?;
+
+        Ok((latency.summary(elapsed), updated))
     }
 
-    pub fn level_benchmark(
+    // Delete `config.deletes` distinct random records by id, returning the
+    // phase summary and how many deletes hit an existing record
+    async fn delete_phase(
         &self,
-        db: &Arc<Mutex<LevelDB<i32>>>,
-    ) -> Result<BenchmarkResult, error::Error> {
-        let start_clear = Instant::now();
-        db.clear_users()?;
-        let clear_time_s = start_clear.elapsed().as_secs_f64();
-        let start_write = Instant::now();
-        for user in &self.data {
-            db.insert_user(user)?;
+        backend: &dyn StorageBackend,
+        config: &BenchmarkConfig,
+    ) -> Result<(PhaseSummary, usize), error::Error> {
+        let users: Vec<&User> = self
+            .data
+            .choose_multiple(&mut rng(config.seed), config.deletes)
+            .collect();
+
+        let mut latency = LatencyHistogram::default();
+        let mut deleted = 0;
+        let elapsed = run_phase(
+            users,
+            config.target_rate,
+            config.concurrency,
+            |user| backend.delete_user(&user.id),
+            |result, elapsed| {
+                deleted += result? as usize;
+                latency.record(elapsed);
+                Ok(())
+            },
+        )
+        .await?;
+
+        Ok((latency.summary(elapsed), deleted))
+    }
+
+    // Run `config.queries` range queries over random age windows, returning the
+    // phase summary, the rows returned and the rows the loaded data says match
+    async fn query_phase(
+        &self,
+        backend: &dyn StorageBackend,
+        config: &BenchmarkConfig,
+    ) -> Result<(PhaseSummary, usize, usize), error::Error> {
+        // Active users per age, to know how many rows each window should return
+        let mut active_by_age: BTreeMap<i32, usize> = BTreeMap::new();
+        for user in self.data.iter().filter(|u| u.active) {
+            *active_by_age.entry(user.age).or_default() += 1;
         }
-        let insert_time_s = start_write.elapsed().as_secs_f64();
-        let start_read = Instant::now();
-        db.read_users()?;
-        let read_time_s = start_read.elapsed().as_secs_f64();
-        Ok(BenchmarkResult {
-            insert_time_s,
-            read_time_s,
-            clear_time_s,
-        })
+        let min_age = self.data.iter().map(|u| u.age).min().unwrap_or(0);
+        let max_age = self.data.iter().map(|u| u.age).max().unwrap_or(0);
+
+        let mut rng = rng(config.seed);
+        let span = config.age_span.min(i32::MAX as u32) as i32;
+        let windows: Vec<(i32, i32)> = (0..config.queries)
+            .map(|_| {
+                let low = rng.gen_range(min_age..=max_age);
+                (low, low.saturating_add(span - 1))
+            })
+            .collect();
+        let expected = windows
+            .iter()
+            .map(|&(low, high)| {
+                active_by_age
+                    .range(low..=high)
+                    .map(|(_, n)| n)
+                    .sum::<usize>()
+            })
+            .sum();
+
+        let mut latency = LatencyHistogram::default();
+        let mut rows = 0;
+        let elapsed = run_phase(
+            windows,
+            config.target_rate,
+            config.concurrency,
+            |(low, high)| backend.find_active_by_age(low, high),
+            |result, elapsed| {
+                rows += result?.len();
+                latency.record(elapsed);
+                Ok(())
+            },
+        )
+        .await?;
+
+        Ok((latency.summary(elapsed), rows, expected))
     }
 }
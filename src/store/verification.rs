@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::store::user_struct::User;
+
+// Cap on individual discrepancies listed in a report; the counts stay exact
+const MAX_EXAMPLES: usize = 20;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    Missing,
+    Extra,
+    Mismatched,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Discrepancy {
+    pub id: String,
+    pub kind: DiscrepancyKind,
+    // Fields that differ, for mismatched records
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<&'static str>,
+}
+
+// Outcome of comparing a backend's read-back against the loaded users
+#[derive(Debug, Default, Serialize)]
+pub struct VerificationReport {
+    pub missing: usize,
+    // Unknown ids, or ids returned more than once
+    pub extra: usize,
+    pub mismatched: usize,
+    pub examples: Vec<Discrepancy>,
+}
+
+impl VerificationReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing == 0 && self.extra == 0 && self.mismatched == 0
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "verification found {} missing, {} extra and {} mismatched records",
+            self.missing, self.extra, self.mismatched
+        )
+    }
+
+    fn record(&mut self, id: &str, kind: DiscrepancyKind, fields: Vec<&'static str>) {
+        match kind {
+            DiscrepancyKind::Missing => self.missing += 1,
+            DiscrepancyKind::Extra => self.extra += 1,
+            DiscrepancyKind::Mismatched => self.mismatched += 1,
+        }
+        if self.examples.len() < MAX_EXAMPLES {
+            self.examples.push(Discrepancy {
+                id: id.to_string(),
+                kind,
+                fields,
+            });
+        }
+    }
+}
+
+fn differing_fields(expected: &User, actual: &User) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if expected.name != actual.name {
+        fields.push("name");
+    }
+    if expected.email != actual.email {
+        fields.push("email");
+    }
+    if expected.age != actual.age {
+        fields.push("age");
+    }
+    if expected.active != actual.active {
+        fields.push("active");
+    }
+    if expected.padding != actual.padding {
+        fields.push("padding");
+    }
+    if expected.profile != actual.profile {
+        fields.push("profile");
+    }
+    if expected.tags != actual.tags {
+        fields.push("tags");
+    }
+    if expected.blob != actual.blob {
+        fields.push("blob");
+    }
+    fields
+}
+
+/// Compare `actual` against `expected` by id, field by field
+pub fn verify_users(expected: &[User], actual: &[User]) -> VerificationReport {
+    let by_id: HashMap<&str, &User> = expected.iter().map(|u| (u.id.as_str(), u)).collect();
+    let mut seen: HashSet<&str> = HashSet::with_capacity(actual.len());
+    let mut report = VerificationReport::default();
+
+    for user in actual {
+        match by_id.get(user.id.as_str()) {
+            Some(original) if seen.insert(user.id.as_str()) => {
+                let fields = differing_fields(original, user);
+                if !fields.is_empty() {
+                    report.record(&user.id, DiscrepancyKind::Mismatched, fields);
+                }
+            }
+            _ => report.record(&user.id, DiscrepancyKind::Extra, Vec::new()),
+        }
+    }
+
+    for user in expected {
+        if !seen.contains(user.id.as_str()) {
+            report.record(&user.id, DiscrepancyKind::Missing, Vec::new());
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::generator::test_users;
+
+    fn kinds(report: &VerificationReport, id: &str) -> Vec<String> {
+        report
+            .examples
+            .iter()
+            .filter(|d| d.id == id)
+            .map(|d| format!("{:?}", d.kind))
+            .collect()
+    }
+
+    #[test]
+    fn identical_records_are_clean() {
+        let expected = test_users(10, 9);
+        let mut actual = expected.clone();
+        actual.reverse();
+        let report = verify_users(&expected, &actual);
+        assert!(report.is_clean(), "{}", report.summary());
+        assert!(report.examples.is_empty());
+    }
+
+    #[test]
+    fn missing_and_unknown_ids_are_counted() {
+        let expected = test_users(10, 9);
+        let mut actual = expected[1..].to_vec();
+        let mut stranger = test_users(11, 9).pop().unwrap();
+        stranger.id = "not-loaded".to_string();
+        actual.push(stranger);
+
+        let report = verify_users(&expected, &actual);
+        assert_eq!((report.missing, report.extra, report.mismatched), (1, 1, 0));
+        assert_eq!(kinds(&report, &expected[0].id), ["Missing"]);
+        assert_eq!(kinds(&report, "not-loaded"), ["Extra"]);
+    }
+
+    #[test]
+    fn duplicate_ids_count_as_extra() {
+        let expected = test_users(5, 9);
+        let mut actual = expected.clone();
+        actual.push(expected[2].clone());
+        actual.push(expected[2].clone());
+
+        let report = verify_users(&expected, &actual);
+        assert_eq!((report.missing, report.extra, report.mismatched), (0, 2, 0));
+        assert_eq!(kinds(&report, &expected[2].id), ["Extra", "Extra"]);
+    }
+
+    #[test]
+    fn mismatches_list_the_differing_fields() {
+        let expected = test_users(3, 9);
+        let mut actual = expected.clone();
+        actual[0].age += 1;
+        actual[0].email.push('x');
+        actual[1].blob = Some(vec![1, 2, 3]);
+
+        let report = verify_users(&expected, &actual);
+        assert_eq!((report.missing, report.extra, report.mismatched), (0, 0, 2));
+        let fields = |id: &str| {
+            report
+                .examples
+                .iter()
+                .find(|d| d.id == id)
+                .map(|d| d.fields.clone())
+                .unwrap()
+        };
+        assert_eq!(fields(&expected[0].id), ["email", "age"]);
+        assert_eq!(fields(&expected[1].id), ["blob"]);
+    }
+
+    #[test]
+    fn examples_are_capped_but_counts_are_not() {
+        let expected = test_users(MAX_EXAMPLES + 5, 9);
+        let report = verify_users(&expected, &[]);
+        assert_eq!(report.missing, MAX_EXAMPLES + 5);
+        assert_eq!(report.examples.len(), MAX_EXAMPLES);
+    }
+}
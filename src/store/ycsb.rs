@@ -0,0 +1,253 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::{
+    config::{BenchmarkConfig, KeyDistribution, PayloadParams, Workload, ZIPFIAN_THETA},
+    store::{
+        distribution::{KeyChooser, rng},
+        error::Error,
+        latency::{LatencyHistogram, PhaseSummary},
+        pacing::run_phase,
+        payload,
+        user_struct::{User, UserUpdate},
+    },
+    utils::db_operations::storage_backend::{SharedUser, StorageBackend},
+};
+
+// Scan lengths are drawn uniformly from 1..=MAX_SCAN_LENGTH, as in YCSB
+const MAX_SCAN_LENGTH: usize = 100;
+
+// Operation proportions of a YCSB core workload; whatever the listed
+// proportions leave over is read-modify-write
+pub struct Mix {
+    read: f64,
+    update: f64,
+    insert: f64,
+    scan: f64,
+    // Standard key choice, unless the config overrides it
+    distribution: KeyDistribution,
+}
+
+impl Mix {
+    /// The core workload mix, for YCSB workloads
+    pub fn for_workload(workload: Workload) -> Option<Mix> {
+        let mix = |read, update, insert, scan, latest| Mix {
+            read,
+            update,
+            insert,
+            scan,
+            distribution: if latest {
+                KeyDistribution::Latest {
+                    theta: ZIPFIAN_THETA,
+                }
+            } else {
+                KeyDistribution::Zipfian {
+                    theta: ZIPFIAN_THETA,
+                }
+            },
+        };
+        match workload {
+            // Update heavy: 50/50 reads and updates
+            Workload::YcsbA => Some(mix(0.5, 0.5, 0.0, 0.0, false)),
+            // Read mostly: 95/5 reads and updates
+            Workload::YcsbB => Some(mix(0.95, 0.05, 0.0, 0.0, false)),
+            // Read only
+            Workload::YcsbC => Some(mix(1.0, 0.0, 0.0, 0.0, false)),
+            // Read latest: 95/5 reads and inserts, reads favour new records
+            Workload::YcsbD => Some(mix(0.95, 0.0, 0.05, 0.0, true)),
+            // Short ranges: 95/5 scans and inserts
+            Workload::YcsbE => Some(mix(0.0, 0.0, 0.05, 0.95, false)),
+            // Read-modify-write: 50/50 reads and read-modify-writes
+            Workload::YcsbF => Some(mix(0.5, 0.0, 0.0, 0.0, false)),
+            _ => None,
+        }
+    }
+}
+
+// One operation, owning what it needs so it can run while others are issued
+enum Op {
+    Read { id: String },
+    Update { id: String, update: UserUpdate },
+    Insert { user: SharedUser },
+    Scan { start: String, length: usize },
+    ReadModifyWrite { id: String },
+}
+
+impl Op {
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Read { .. } => "read",
+            Op::Update { .. } => "update",
+            Op::Insert { .. } => "insert",
+            Op::Scan { .. } => "scan",
+            Op::ReadModifyWrite { .. } => "read_modify_write",
+        }
+    }
+}
+
+// Run `op`, returning it with whether it found (or wrote) its record
+async fn run_op(backend: &dyn StorageBackend, op: Op) -> (Op, Result<bool, Error>) {
+    let result = match &op {
+        Op::Read { id } => backend.get_user(id).await.map(|user| user.is_some()),
+        Op::Update { id, update } => backend.update_user(id, update).await,
+        Op::Insert { user } => backend.insert_user(user.clone()).await.map(|_| true),
+        Op::Scan { start, length } => backend
+            .scan_users(start, *length)
+            .await
+            .map(|users| !users.is_empty()),
+        Op::ReadModifyWrite { id } => match backend.get_user(id).await {
+            Ok(Some(user)) => backend.update_user(id, &UserUpdate::for_user(&user)).await,
+            Ok(None) => Ok(false),
+            Err(e) => Err(e),
+        },
+    };
+    (op, result)
+}
+
+// Record inserted by the workload, with the configured payload; ids are
+// drawn from the seeded generator
+fn new_user(rng: &mut impl Rng, n: usize, payload: Option<&PayloadParams>) -> User {
+    let mut user = User {
+        id: format!("ycsb-{:016x}{:016x}", rng.gen::<u64>(), rng.gen::<u64>()),
+        name: format!("YCSB User {}", n),
+        email: format!("ycsb{}@example.com", n),
+        age: rng.gen_range(18..=80),
+        active: rng.gen(),
+        padding: None,
+        profile: None,
+        tags: None,
+        blob: None,
+    };
+    if let Some(params) = payload {
+        payload::fill(&mut user, params, rng);
+    }
+    user
+}
+
+// Outcome of a YCSB run
+#[derive(Debug, Serialize)]
+pub struct YcsbReport {
+    pub operations: usize,
+    pub distribution: KeyDistribution,
+    pub runtime_s: f64,
+    pub ops_per_sec: f64,
+    // Records added by insert operations, still stored when the run ends
+    pub inserted: usize,
+    // Operations whose record was not found
+    pub misses: usize,
+    // Latency and throughput per operation type
+    pub ops: BTreeMap<&'static str, PhaseSummary>,
+}
+
+// Per-run state updated as operations complete
+#[derive(Default)]
+struct Tally {
+    latency: BTreeMap<&'static str, LatencyHistogram>,
+    misses: usize,
+    // Inserted records become readable once their insert has completed
+    inserted: Vec<SharedUser>,
+}
+
+impl Tally {
+    fn complete(
+        &mut self,
+        (op, result): (Op, Result<bool, Error>),
+        elapsed: Duration,
+    ) -> Result<(), Error> {
+        if !result? {
+            self.misses += 1;
+        }
+        self.latency.entry(op.name()).or_default().record(elapsed);
+        if let Op::Insert { user } = op {
+            self.inserted.push(user);
+        }
+        Ok(())
+    }
+}
+
+/// Run `config.operations` operations of `mix` over the `loaded` records,
+/// keeping up to `config.concurrency` in flight
+pub async fn run(
+    loaded: &[User],
+    backend: &dyn StorageBackend,
+    config: &BenchmarkConfig,
+    mix: &Mix,
+) -> Result<YcsbReport, Error> {
+    let mut rng = rng(config.seed);
+    let distribution = config.distribution.unwrap_or(mix.distribution);
+    let mut keys = KeyChooser::new(distribution, loaded.len());
+    // Issuing reads the inserted records and completing adds to them; the
+    // phase runs one closure at a time, so the lock is never contended
+    let tally = Mutex::new(Tally::default());
+    let mut issued_inserts = 0;
+
+    let elapsed = run_phase(
+        0..config.operations,
+        config.target_rate,
+        config.concurrency,
+        |_| {
+            let tally = tally.lock().unwrap_or_else(PoisonError::into_inner);
+            let items = loaded.len() + tally.inserted.len();
+            let key = keys.next(&mut rng, items);
+            let record = match loaded.get(key) {
+                Some(user) => user,
+                None => &*tally.inserted[key - loaded.len()],
+            };
+
+            let choice: f64 = rng.gen();
+            let op = if choice < mix.read {
+                Op::Read {
+                    id: record.id.clone(),
+                }
+            } else if choice < mix.read + mix.update {
+                Op::Update {
+                    id: record.id.clone(),
+                    update: UserUpdate::for_user(record),
+                }
+            } else if choice < mix.read + mix.update + mix.insert {
+                issued_inserts += 1;
+                Op::Insert {
+                    user: new_user(&mut rng, issued_inserts, config.payload.as_ref()).into(),
+                }
+            } else if choice < mix.read + mix.update + mix.insert + mix.scan {
+                Op::Scan {
+                    start: record.id.clone(),
+                    length: rng.gen_range(1..=MAX_SCAN_LENGTH),
+                }
+            } else {
+                Op::ReadModifyWrite {
+                    id: record.id.clone(),
+                }
+            };
+            run_op(backend, op)
+        },
+        |outcome, elapsed| {
+            tally
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .complete(outcome, elapsed)
+        },
+    )
+    .await?;
+    let tally = tally.into_inner().unwrap_or_else(PoisonError::into_inner);
+
+    Ok(YcsbReport {
+        operations: config.operations,
+        distribution,
+        runtime_s: elapsed.as_secs_f64(),
+        ops_per_sec: config.operations as f64 / elapsed.as_secs_f64().max(f64::MIN_POSITIVE),
+        inserted: tally.inserted.len(),
+        misses: tally.misses,
+        ops: tally
+            .latency
+            .iter()
+            .map(|(name, histogram)| (*name, histogram.summary(elapsed)))
+            .collect(),
+    })
+}
@@ -13,6 +13,8 @@ use sqlx::{postgres::PgPoolOptions, PgPool};
 use std::path::Path;
 use surrealdb::{engine::remote::ws::Ws, opt::auth::Root, Surreal};
 
+use crate::utils::db_operations::level_db_operations::BytesKey;
+
 pub async fn connect_to_pgsql() -> Result<PgPool, sqlx::Error> {
     let database_url = match std::env::var("DATABASE_URL") {
         Ok(url) => url,
@@ -25,15 +27,23 @@ pub async fn connect_to_pgsql() -> Result<PgPool, sqlx::Error> {
         }
     };
 
+    // Size the pool for the highest benchmark concurrency expected; results
+    // at a higher concurrency report this limit
+    let max_connections = std::env::var("PG_MAX_CONNECTIONS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(10);
+
     let pg_pool = PgPoolOptions::new()
-        .max_connections(10)
+        .max_connections(max_connections)
         .connect(&database_url)
         .await?;
 
     Ok(pg_pool)
 }
 
-pub async fn connect_to_mongodb() -> Result<Database, mongodb::error::Error> {
+// Returns the database with the size of the client's connection pool
+pub async fn connect_to_mongodb() -> Result<(Database, u32), mongodb::error::Error> {
     let uri = match std::env::var("MONGODB_URL") {
         Ok(url) => url,
         Err(e) => {
@@ -50,6 +60,16 @@ pub async fn connect_to_mongodb() -> Result<Database, mongodb::error::Error> {
     let server_api = ServerApi::builder().version(ServerApiVersion::V1).build();
     client_options.server_api = Some(server_api);
 
+    // Like PG_MAX_CONNECTIONS; otherwise `maxPoolSize` in the URL or the
+    // driver's default of 10
+    if let Some(max_pool_size) = std::env::var("MONGO_MAX_POOL_SIZE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+    {
+        client_options.max_pool_size = Some(max_pool_size);
+    }
+    let max_pool_size = client_options.max_pool_size.unwrap_or(10);
+
     // Create a new client and connect to the server
     let client = Client::with_options(client_options)?;
 
@@ -62,7 +82,7 @@ pub async fn connect_to_mongodb() -> Result<Database, mongodb::error::Error> {
     println!("Pinged your deployment. You successfully connected to MongoDB!");
 
     //Return the database for use
-    Ok(client.database("testdb"))
+    Ok((client.database("testdb"), max_pool_size))
 }
 
 pub async fn connect_to_surrealdb(
@@ -101,7 +121,8 @@ pub fn connect_to_rocksdb() -> Result<RocksDB, rocksdb::Error> {
     Ok(db)
 }
 
-pub fn connect_to_leveldb() -> Result<Arc<Mutex<LevelDB<i32>>>, Box<dyn std::error::Error>> {
+pub fn connect_to_leveldb() -> Result<Arc<Mutex<LevelDB<BytesKey>>>, Box<dyn std::error::Error>>
+{
     let mut opts = LevelOptions::new();
     opts.create_if_missing = true;
 
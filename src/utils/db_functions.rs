@@ -1,105 +1,102 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
-use mongodb::Database;
-use sqlx::PgPool;
-use surrealdb::Surreal;
-
-use crate::utils::connect_to_db::{
-    connect_to_leveldb, connect_to_mongodb, connect_to_pgsql, connect_to_rocksdb,
-    connect_to_surrealdb,
+use crate::{
+    store::backend_registry::{BackendConnector, BackendRegistry, parse_backend_list},
+    utils::{
+        connect_to_db::{
+            connect_to_leveldb, connect_to_mongodb, connect_to_pgsql, connect_to_rocksdb,
+            connect_to_surrealdb,
+        },
+        db_operations::{
+            level_db_operations::LevelBackend,
+            mongo_db_operations::MongoBackend,
+            psql_db_operations::PgBackend,
+            rocks_db_operations::RocksBackend,
+            storage_backend::{Blocking, StorageBackend},
+            surreal_db_operations::SurrealBackend,
+        },
+    },
 };
 
-use leveldb::database::Database as LevelDB;
-use rocksdb::DB as RocksDB;
+// Every backend the server knows how to connect to, in benchmark order
+pub const CONNECTORS: [BackendConnector; 5] = [
+    BackendConnector {
+        key: "postgres",
+        name: "PostgreSQL",
+        connect: || {
+            Box::pin(async {
+                let pool = connect_to_pgsql().await?;
+                Ok(Arc::new(PgBackend::new(pool)) as Arc<dyn StorageBackend>)
+            })
+        },
+    },
+    BackendConnector {
+        key: "mongodb",
+        name: "MongoDB",
+        connect: || {
+            Box::pin(async {
+                let (db, max_pool_size) = connect_to_mongodb().await?;
+                Ok(Arc::new(MongoBackend::new(db, max_pool_size)) as Arc<dyn StorageBackend>)
+            })
+        },
+    },
+    BackendConnector {
+        key: "surrealdb",
+        name: "SurrealDB",
+        connect: || {
+            Box::pin(async {
+                let db = connect_to_surrealdb().await?;
+                Ok(Arc::new(SurrealBackend::new(db)) as Arc<dyn StorageBackend>)
+            })
+        },
+    },
+    BackendConnector {
+        key: "rocksdb",
+        name: "RocksDB",
+        connect: || {
+            Box::pin(async {
+                let db = connect_to_rocksdb()?;
+                Ok(Arc::new(Blocking::new(RocksBackend::new(db))) as Arc<dyn StorageBackend>)
+            })
+        },
+    },
+    BackendConnector {
+        key: "leveldb",
+        name: "LevelDB",
+        connect: || {
+            Box::pin(async {
+                let db = connect_to_leveldb().map_err(|e| e.to_string())?;
+                Ok(Arc::new(Blocking::new(LevelBackend::new(db))) as Arc<dyn StorageBackend>)
+            })
+        },
+    },
+];
 
 pub struct Databases {
-    pub pg_pool: PgPool,
-    pub mongo_db_connection: Database,
-    pub surreal_db_connection: Surreal<surrealdb::engine::remote::ws::Client>,
-    pub rocks_db_connection: RocksDB,
-    pub level_db_connection: Arc<Mutex<LevelDB<i32>>>,
+    pub registry: BackendRegistry,
 }
 
 impl Databases {
+    /// Connect the backends listed in `BACKENDS` (comma-separated keys), or all of them.
+    /// Backends that fail to connect stay registered as unavailable.
     pub async fn new() -> Self {
-        let pg_pool = match connect_to_pgsql().await {
-            Ok(pool) => {
-                eprint!("Connected to PostgreSQL\n");
-                pool
-            }
-            Err(e) => {
-                eprintln!("Failed to connect to PostgreSQL: {}", e);
-                std::process::exit(1);
-            }
-        };
-
-        let mongo_db_connection = match connect_to_mongodb().await {
-            Ok(db) => {
-                eprint!("Connected to MongoDB\n");
-                db
-            }
-            Err(e) => {
-                eprintln!("Failed to connect to MongoDB: {}", e);
-                std::process::exit(1);
-            }
+        let enabled = match std::env::var("BACKENDS") {
+            Ok(list) => parse_backend_list(&list),
+            Err(_) => Vec::new(),
         };
 
-        let surreal_db_connection = match connect_to_surrealdb().await {
-            Ok(db) => {
-                eprint!("Connected to SurrealDB\n");
-                db
-            }
-            Err(e) => {
-                eprintln!("Failed to connect to SurrealDB: {}", e);
-                std::process::exit(1);
-            }
-        };
-        let rocks_db_connection = match connect_to_rocksdb() {
-            Ok(db) => {
-                eprint!("Connected to RocksDB\n");
-                db
+        for key in &enabled {
+            if !CONNECTORS.iter().any(|c| c.key == key) {
+                eprintln!("Unknown backend '{}' in BACKENDS, ignoring", key);
             }
-            Err(e) => {
-                eprintln!("Failed to connect to RocksDB: {}", e);
-                std::process::exit(1);
-            }
-        };
-
-        let level_db_connection = match connect_to_leveldb() {
-            Ok(db) => {
-                eprint!("Connected to LevelDB\n");
-                db
-            }
-            Err(e) => {
-                eprintln!("Failed to connect to LevelDB: {}", e);
-                std::process::exit(1);
-            }
-        };
-
-        Self {
-            pg_pool,
-            mongo_db_connection,
-            surreal_db_connection,
-            rocks_db_connection,
-            level_db_connection,
         }
-    }
 
-    pub async fn postgres_tables_setup(&self) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            email TEXT NOT NULL,
-            age INT NOT NULL,
-            active BOOLEAN NOT NULL
-        )
-    "#,
-        )
-        .execute(&self.pg_pool)
-        .await?;
+        let connectors = CONNECTORS
+            .into_iter()
+            .filter(|c| enabled.is_empty() || enabled.iter().any(|key| key == c.key));
+
+        let registry = BackendRegistry::connect(connectors).await;
 
-        Ok(())
+        Self { registry }
     }
 }
@@ -0,0 +1,153 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use mongodb::bson;
+
+use crate::store::user_struct::User;
+
+// Key layout, record encoding and index maintenance shared by the embedded
+// key-value engines (RocksDB, LevelDB)
+
+pub const USER_PREFIX: &[u8] = b"user:";
+// Secondary index on (active, age): `idx:active_age:<active><age><id>` -> empty
+pub const ACTIVE_AGE_PREFIX: &[u8] = b"idx:active_age:";
+// Length of an index key before the id: prefix, active flag, age
+const ACTIVE_AGE_HEAD: usize = ACTIVE_AGE_PREFIX.len() + 1 + 4;
+
+/// Key of a user record: `user:<id>`
+pub fn user_key(id: &str) -> Vec<u8> {
+    let mut key = USER_PREFIX.to_vec();
+    key.extend_from_slice(id.as_bytes());
+    key
+}
+
+/// Stored form of a user record: BSON, as MongoDB stores it, so a binary
+/// payload takes its own size rather than a JSON array of numbers
+pub fn encode_user(user: &User) -> Result<Vec<u8>, bson::ser::Error> {
+    bson::to_vec(user)
+}
+
+pub fn decode_user(value: &[u8]) -> Result<User, bson::de::Error> {
+    bson::from_slice(value)
+}
+
+// Big-endian with the sign bit flipped, so byte order matches numeric order
+fn sortable_age(age: i32) -> [u8; 4] {
+    ((age as u32) ^ 0x8000_0000).to_be_bytes()
+}
+
+fn active_age_head(active: bool, age: i32) -> Vec<u8> {
+    let mut key = ACTIVE_AGE_PREFIX.to_vec();
+    key.push(active as u8);
+    key.extend_from_slice(&sortable_age(age));
+    key
+}
+
+/// Index entry of `user` in the (active, age) index
+pub fn active_age_key(user: &User) -> Vec<u8> {
+    let mut key = active_age_head(user.active, user.age);
+    key.extend_from_slice(user.id.as_bytes());
+    key
+}
+
+/// Index bounds for active users aged `min_age..=max_age`: seek to the first,
+/// then stop at the first key for which `active_age_in_range` is false
+pub fn active_age_range(min_age: i32, max_age: i32) -> (Vec<u8>, Vec<u8>) {
+    (
+        active_age_head(true, min_age),
+        active_age_head(true, max_age),
+    )
+}
+
+pub fn active_age_in_range(key: &[u8], end: &[u8]) -> bool {
+    key.len() >= ACTIVE_AGE_HEAD
+        && key.starts_with(ACTIVE_AGE_PREFIX)
+        && key[..ACTIVE_AGE_HEAD] <= *end
+}
+
+/// Key of the user record an index entry points at
+pub fn user_key_for_index_entry(key: &[u8]) -> Vec<u8> {
+    let mut user_key = USER_PREFIX.to_vec();
+    user_key.extend_from_slice(&key[ACTIVE_AGE_HEAD..]);
+    user_key
+}
+
+// Write batch of an engine, as the index maintenance below fills it
+pub trait KvBatch {
+    fn put(&mut self, key: Vec<u8>, value: &[u8]);
+    fn delete(&mut self, key: Vec<u8>);
+}
+
+// Whether the (active, age) index exists and must be kept in sync on writes
+pub struct ActiveAgeIndex {
+    built: AtomicBool,
+}
+
+impl ActiveAgeIndex {
+    /// State of a store whose first key at or after `ACTIVE_AGE_PREFIX` is
+    /// `first_key`; an index left by an earlier process is still maintained
+    pub fn detect(first_key: Option<&[u8]>) -> Self {
+        Self {
+            built: AtomicBool::new(first_key.is_some_and(|key| key.starts_with(ACTIVE_AGE_PREFIX))),
+        }
+    }
+
+    pub fn is_built(&self) -> bool {
+        self.built.load(Ordering::Acquire)
+    }
+
+    pub fn set_built(&self, built: bool) {
+        self.built.store(built, Ordering::Release);
+    }
+
+    /// Add the record, stored as `value`, and when built its index entry
+    pub fn put_user(&self, batch: &mut impl KvBatch, user: &User, value: &[u8]) {
+        batch.put(user_key(&user.id), value);
+        if self.is_built() {
+            batch.put(active_age_key(user), b"");
+        }
+    }
+
+    /// Drop the index entry of `user`, before an update that may move it
+    pub fn remove_entry(&self, batch: &mut impl KvBatch, user: &User) {
+        if self.is_built() {
+            batch.delete(active_age_key(user));
+        }
+    }
+
+    /// Remove the record and its index entry. Deletes are blind writes, so
+    /// callers look the record up first: misses are reported and the entry found.
+    pub fn delete_user(&self, batch: &mut impl KvBatch, user: &User) {
+        batch.delete(user_key(&user.id));
+        if self.is_built() {
+            batch.delete(active_age_key(user));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::PayloadParams,
+        store::{distribution::rng, generator::test_users, payload},
+    };
+
+    #[test]
+    fn binary_payload_is_stored_at_its_own_size() {
+        let params = PayloadParams {
+            size: 64 * 1024,
+            padding: false,
+            blob: true,
+            ..PayloadParams::default()
+        };
+        let mut user = test_users(1, 7).remove(0);
+        let bare = encode_user(&user).unwrap().len();
+        payload::fill(&mut user, &params, &mut rng(Some(7)));
+
+        let encoded = encode_user(&user).unwrap();
+        assert!(encoded.len() - bare <= params.size + 32);
+        let decoded = decode_user(&encoded).unwrap();
+        assert_eq!(decoded.blob, user.blob);
+        assert_eq!(decoded.id, user.id);
+    }
+}
@@ -1,10 +1,27 @@
+use db_key::Key;
+use leveldb::batch::{Batch, Writebatch};
 use leveldb::database::Database as LevelDB;
-use leveldb::iterator::Iterable;
+use leveldb::iterator::{Iterable, LevelDBIterator};
 use leveldb::kv::KV;
 use leveldb::options::{ReadOptions, WriteOptions};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 
-use crate::store::user_struct::User;
+use crate::{
+    store::{
+        error::Error,
+        user_struct::{User, UserUpdate},
+    },
+    utils::db_operations::{
+        kv_keys::{
+            ACTIVE_AGE_PREFIX, ActiveAgeIndex, KvBatch, USER_PREFIX, active_age_in_range,
+            active_age_key, active_age_range, decode_user, encode_user, user_key,
+            user_key_for_index_entry,
+        },
+        storage_backend::BlockingBackend,
+    },
+};
+
+const NAME: &str = "LevelDB";
 
 // Custom error type for LevelDB operations
 #[derive(Debug)]
@@ -24,88 +41,386 @@ impl std::fmt::Display for LevelError {
     }
 }
 
+impl std::error::Error for LevelError {}
+
+impl From<leveldb::error::Error> for LevelError {
+    fn from(err: leveldb::error::Error) -> Self {
+        LevelError::Db(format!("{:?}", err))
+    }
+}
+
+impl From<LevelError> for Error {
+    fn from(err: LevelError) -> Self {
+        Error::backend(NAME, err)
+    }
+}
+
+// Raw byte-string key, so records are keyed by the full user id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BytesKey(pub Vec<u8>);
+
+impl BytesKey {
+    /// Key of a user record: `user:<id>`, the same layout RocksDB uses
+    pub fn user(id: &str) -> Self {
+        BytesKey(user_key(id))
+    }
+
+    pub fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.0.starts_with(prefix)
+    }
+}
+
+impl Key for BytesKey {
+    fn from_u8(key: &[u8]) -> Self {
+        BytesKey(key.to_vec())
+    }
+
+    fn as_slice<T, F: Fn(&[u8]) -> T>(&self, f: F) -> T {
+        f(&self.0)
+    }
+}
+
+impl KvBatch for Writebatch<BytesKey> {
+    fn put(&mut self, key: Vec<u8>, value: &[u8]) {
+        Writebatch::put(self, BytesKey(key), value);
+    }
+
+    fn delete(&mut self, key: Vec<u8>) {
+        Writebatch::delete(self, BytesKey(key));
+    }
+}
+
+fn serialize(user: &User) -> Result<Vec<u8>, LevelError> {
+    encode_user(user).map_err(|e| LevelError::Serialization(e.to_string()))
+}
+
+fn deserialize(value: &[u8]) -> Result<User, LevelError> {
+    decode_user(value).map_err(|e| LevelError::Serialization(e.to_string()))
+}
+
+// Values stored under `prefix`
+fn scan_values(db: &LevelDB<BytesKey>, prefix: &[u8]) -> Vec<Vec<u8>> {
+    let from = BytesKey(prefix.to_vec());
+    db.iter(ReadOptions::new())
+        .from(&from)
+        .take_while(|(key, _)| key.starts_with(prefix))
+        .map(|(_, value)| value)
+        .collect()
+}
+
+// Delete every key under `prefix` in one batch, returning how many there were
+fn delete_prefix(db: &LevelDB<BytesKey>, prefix: &[u8]) -> Result<usize, LevelError> {
+    let from = BytesKey(prefix.to_vec());
+    let keys: Vec<BytesKey> = db
+        .keys_iter(ReadOptions::new())
+        .from(&from)
+        .take_while(|key| key.starts_with(prefix))
+        .collect();
+
+    let deleted = keys.len();
+    let mut batch = Writebatch::new();
+    for key in keys {
+        batch.delete(key);
+    }
+    db.write(WriteOptions::new(), &batch)?;
+    Ok(deleted)
+}
+
 //LevelDB Operations
-pub trait LevelOperations {
-    fn insert_user(&self, user: &User) -> Result<(), LevelError>;
-    fn read_users(&self) -> Result<Vec<User>, LevelError>;
-    fn clear_users(&self) -> Result<(), LevelError>;
+pub struct LevelBackend {
+    db: Arc<Mutex<LevelDB<BytesKey>>>,
+    index: ActiveAgeIndex,
 }
 
-impl LevelOperations for Arc<Mutex<LevelDB<i32>>> {
-    fn insert_user(&self, user: &User) -> Result<(), LevelError> {
-        let value = match serde_json::to_vec(user) {
-            Ok(v) => v,
-            Err(e) => return Err(LevelError::Serialization(e.to_string())),
+impl LevelBackend {
+    pub fn new(db: Arc<Mutex<LevelDB<BytesKey>>>) -> Self {
+        let first_key = match db.lock() {
+            Ok(db) => {
+                let from = BytesKey(ACTIVE_AGE_PREFIX.to_vec());
+                db.keys_iter(ReadOptions::new()).from(&from).next()
+            }
+            Err(_) => None,
         };
+        let index = ActiveAgeIndex::detect(first_key.as_ref().map(|key| key.0.as_slice()));
+        Self { db, index }
+    }
 
-        let db = match self.lock() {
-            Ok(db) => db,
-            Err(e) => return Err(LevelError::Lock(e.to_string())),
-        };
+    fn lock(&self) -> Result<MutexGuard<'_, LevelDB<BytesKey>>, LevelError> {
+        self.db.lock().map_err(|e| LevelError::Lock(e.to_string()))
+    }
 
-        // FNV-1a hash: UUID string → i32 key
-        let key = user
-            .id
-            .as_bytes()
-            .iter()
-            .fold(2166136261u32, |hash, &byte| {
-                (hash ^ (byte as u32)).wrapping_mul(16777619)
-            }) as i32;
+    fn get(db: &LevelDB<BytesKey>, key: BytesKey) -> Result<Option<User>, LevelError> {
+        match db.get(ReadOptions::new(), key)? {
+            Some(value) => Ok(Some(deserialize(&value)?)),
+            None => Ok(None),
+        }
+    }
+}
 
-        let write_opts = WriteOptions::new();
+impl BlockingBackend for LevelBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn ping(&self) -> Result<(), Error> {
+        let db = self.lock()?;
+        db.get(ReadOptions::new(), BytesKey(USER_PREFIX.to_vec()))
+            .map_err(LevelError::from)?;
+        Ok(())
+    }
+
+    fn insert_user(&self, user: &User) -> Result<(), Error> {
+        let mut batch = Writebatch::new();
+        self.index.put_user(&mut batch, user, &serialize(user)?);
+
+        let db = self.lock()?;
+        db.write(WriteOptions::new(), &batch)
+            .map_err(LevelError::from)?;
+        Ok(())
+    }
 
-        match db.put(write_opts, key, &value) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(LevelError::Db(format!("{:?}", e))),
+    fn insert_users(&self, users: &[User]) -> Result<usize, Error> {
+        let mut batch = Writebatch::new();
+        for user in users {
+            self.index.put_user(&mut batch, user, &serialize(user)?);
         }
+
+        let db = self.lock()?;
+        db.write(WriteOptions::new(), &batch)
+            .map_err(LevelError::from)?;
+        Ok(users.len())
     }
 
-    fn read_users(&self) -> Result<Vec<User>, LevelError> {
+    fn read_users(&self) -> Result<Vec<User>, Error> {
+        let db = self.lock()?;
+
         let mut users = Vec::new();
+        for value in scan_values(&db, USER_PREFIX) {
+            users.push(deserialize(&value)?);
+        }
 
-        let db = match self.lock() {
-            Ok(db) => db,
-            Err(e) => return Err(LevelError::Lock(e.to_string())),
-        };
+        Ok(users)
+    }
 
-        let read_opts = ReadOptions::new();
-        let iter = db.iter(read_opts);
+    fn get_user(&self, id: &str) -> Result<Option<User>, Error> {
+        let db = self.lock()?;
+        Ok(Self::get(&db, BytesKey::user(id))?)
+    }
+
+    fn scan_users(&self, start_id: &str, limit: usize) -> Result<Vec<User>, Error> {
+        let db = self.lock()?;
+        let start = BytesKey::user(start_id);
 
-        for (_, value) in iter {
-            let user: User = match serde_json::from_slice(&value) {
-                Ok(u) => u,
-                Err(e) => return Err(LevelError::Serialization(e.to_string())),
-            };
-            users.push(user);
+        let mut users = Vec::new();
+        for (_, value) in db
+            .iter(ReadOptions::new())
+            .from(&start)
+            .take_while(|(key, _)| key.starts_with(USER_PREFIX))
+            .take(limit)
+        {
+            users.push(deserialize(&value)?);
         }
 
         Ok(users)
     }
 
-    fn clear_users(&self) -> Result<(), LevelError> {
-        let db = match self.lock() {
-            Ok(db) => db,
-            Err(e) => return Err(LevelError::Lock(e.to_string())),
+    fn update_user(&self, id: &str, update: &UserUpdate) -> Result<bool, Error> {
+        // Read, modify and write back under one lock so the update is atomic
+        let db = self.lock()?;
+        let mut user = match Self::get(&db, BytesKey::user(id))? {
+            Some(u) => u,
+            None => return Ok(false),
         };
 
-        let read_opts = ReadOptions::new();
-        let keys_to_delete: Vec<i32> = db.iter(read_opts).map(|(k, _)| k).collect();
-
-        drop(db);
+        let mut batch = Writebatch::new();
+        self.index.remove_entry(&mut batch, &user);
+        update.apply(&mut user);
+        self.index.put_user(&mut batch, &user, &serialize(&user)?);
+        db.write(WriteOptions::new(), &batch)
+            .map_err(LevelError::from)?;
+        Ok(true)
+    }
 
-        let db = match self.lock() {
-            Ok(db) => db,
-            Err(e) => return Err(LevelError::Lock(e.to_string())),
+    fn delete_user(&self, id: &str) -> Result<bool, Error> {
+        let db = self.lock()?;
+        let user = match Self::get(&db, BytesKey::user(id))? {
+            Some(u) => u,
+            None => return Ok(false),
         };
 
-        let write_opts = WriteOptions::new();
-        for key in keys_to_delete {
-            match db.delete(write_opts, key) {
-                Ok(_) => {}
-                Err(e) => return Err(LevelError::Db(format!("{:?}", e))),
-            }
+        let mut batch = Writebatch::new();
+        self.index.delete_user(&mut batch, &user);
+        db.write(WriteOptions::new(), &batch)
+            .map_err(LevelError::from)?;
+        Ok(true)
+    }
+
+    fn clear_users(&self) -> Result<usize, Error> {
+        let db = self.lock()?;
+        let cleared = delete_prefix(&db, USER_PREFIX)?;
+        delete_prefix(&db, ACTIVE_AGE_PREFIX)?;
+        Ok(cleared)
+    }
+
+    fn create_active_age_index(&self) -> Result<(), Error> {
+        let db = self.lock()?;
+
+        let mut batch = Writebatch::new();
+        for value in scan_values(&db, USER_PREFIX) {
+            batch.put(BytesKey(active_age_key(&deserialize(&value)?)), &[]);
         }
+        db.write(WriteOptions::new(), &batch)
+            .map_err(LevelError::from)?;
+
+        self.index.set_built(true);
+        Ok(())
+    }
 
+    fn drop_active_age_index(&self) -> Result<(), Error> {
+        let db = self.lock()?;
+        self.index.set_built(false);
+        delete_prefix(&db, ACTIVE_AGE_PREFIX)?;
         Ok(())
     }
+
+    fn find_active_by_age(&self, min_age: i32, max_age: i32) -> Result<Vec<User>, Error> {
+        if !self.index.is_built() {
+            // No index: scan every record and filter
+            let users = self.read_users()?;
+            return Ok(users
+                .into_iter()
+                .filter(|u| u.active && (min_age..=max_age).contains(&u.age))
+                .collect());
+        }
+
+        let db = self.lock()?;
+        let (start, end) = active_age_range(min_age, max_age);
+        let start = BytesKey(start);
+        let entries: Vec<BytesKey> = db
+            .keys_iter(ReadOptions::new())
+            .from(&start)
+            .take_while(|key| active_age_in_range(&key.0, &end))
+            .collect();
+
+        let mut users = Vec::new();
+        for key in entries {
+            if let Some(user) = Self::get(&db, BytesKey(user_key_for_index_entry(&key.0)))? {
+                users.push(user);
+            }
+        }
+
+        Ok(users)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{generator::test_users, verification::verify_users};
+    use leveldb::options::Options as LevelOptions;
+    use uuid::Uuid;
+
+    fn open_temp_db() -> (std::path::PathBuf, LevelBackend) {
+        let path = std::env::temp_dir().join(format!("leveldb-test-{}", Uuid::new_v4()));
+        let mut opts = LevelOptions::new();
+        opts.create_if_missing = true;
+        let db = LevelDB::open(&path, opts).expect("open temporary LevelDB");
+        (path, LevelBackend::new(Arc::new(Mutex::new(db))))
+    }
+
+    // The key scheme this replaced: FNV-1a of the id folded into an i32
+    fn fnv1a_i32(id: &str) -> i32 {
+        id.as_bytes().iter().fold(2166136261u32, |hash, &byte| {
+            (hash ^ (byte as u32)).wrapping_mul(16777619)
+        }) as i32
+    }
+
+    #[test]
+    fn large_dataset_round_trips_without_loss() {
+        let (path, backend) = open_temp_db();
+        let users = test_users(200_000, 42);
+
+        for user in &users {
+            backend.insert_user(user).unwrap();
+        }
+        let read_back = backend.read_users().unwrap();
+        let report = verify_users(&users, &read_back);
+        let cleared = backend.clear_users().unwrap();
+
+        drop(backend);
+        let _ = std::fs::remove_dir_all(path);
+
+        assert_eq!(read_back.len(), users.len());
+        assert!(report.is_clean(), "{}", report.summary());
+        assert_eq!(cleared, users.len());
+    }
+
+    #[test]
+    fn ids_with_colliding_hashes_are_both_kept() {
+        // Birthday search for two ids the old scheme would have stored under one key
+        let mut seen = std::collections::HashMap::new();
+        let (first, second) = loop {
+            let id = Uuid::new_v4().to_string();
+            if let Some(other) = seen.insert(fnv1a_i32(&id), id.clone()) {
+                break (other, id);
+            }
+        };
+
+        let (path, backend) = open_temp_db();
+        let mut users = test_users(2, 42);
+        users[0].id = first;
+        users[1].id = second;
+
+        for user in &users {
+            backend.insert_user(user).unwrap();
+        }
+        let report = verify_users(&users, &backend.read_users().unwrap());
+
+        drop(backend);
+        let _ = std::fs::remove_dir_all(path);
+
+        assert!(report.is_clean(), "{}", report.summary());
+    }
+
+    #[test]
+    fn active_age_index_tracks_updates_and_deletes() {
+        let (path, backend) = open_temp_db();
+        let mut users = test_users(2_000, 42);
+
+        backend.insert_users(&users[..1_000]).unwrap();
+        backend.create_active_age_index().unwrap();
+        // Written after the build, so only present if inserts maintain the index
+        backend.insert_users(&users[1_000..]).unwrap();
+
+        for user in users.iter_mut().step_by(3) {
+            let update = UserUpdate::for_user(user);
+            assert!(backend.update_user(&user.id, &update).unwrap());
+            update.apply(user);
+        }
+        for user in users.iter().step_by(7) {
+            assert!(backend.delete_user(&user.id).unwrap());
+        }
+        let remaining: Vec<User> = users
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 7 != 0)
+            .map(|(_, u)| u.clone())
+            .collect();
+
+        let mut found = backend.find_active_by_age(30, 45).unwrap();
+        let mut expected: Vec<User> = remaining
+            .into_iter()
+            .filter(|u| u.active && (30..=45).contains(&u.age))
+            .collect();
+        found.sort_by(|a, b| a.id.cmp(&b.id));
+        expected.sort_by(|a, b| a.id.cmp(&b.id));
+        let report = verify_users(&expected, &found);
+
+        backend.drop_active_age_index().unwrap();
+        drop(backend);
+        let _ = std::fs::remove_dir_all(path);
+
+        assert_eq!(found.len(), expected.len());
+        assert!(report.is_clean(), "{}", report.summary());
+    }
 }
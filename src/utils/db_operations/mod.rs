@@ -1,5 +1,7 @@
+pub mod kv_keys;
 pub mod level_db_operations;
 pub mod mongo_db_operations;
 pub mod psql_db_operations;
 pub mod rocks_db_operations;
+pub mod storage_backend;
 pub mod surreal_db_operations;
@@ -1,32 +1,185 @@
+use async_trait::async_trait;
 use futures::TryStreamExt;
-use mongodb::{Collection, Database, bson::doc};
+use mongodb::{Collection, Database, IndexModel, bson::doc, options::IndexOptions};
 
-use crate::store::user_struct::User;
+use crate::{
+    store::{
+        error::Error,
+        user_struct::{User, UserUpdate},
+    },
+    utils::db_operations::storage_backend::{SharedUser, SharedUsers, StorageBackend},
+};
+
+const NAME: &str = "MongoDB";
+const ACTIVE_AGE_INDEX: &str = "active_age";
+
+fn mongo_err(err: mongodb::error::Error) -> Error {
+    Error::backend(NAME, err)
+}
 
 //MongoDb Operations
-pub trait MongoOperations {
-    fn users_collection(&self) -> Collection<User>;
-    async fn insert_user(&self, user: &User) -> Result<(), mongodb::error::Error>;
-    async fn read_users(&self) -> Result<Vec<User>, mongodb::error::Error>;
-    async fn clear_users(&self) -> Result<(), mongodb::error::Error>;
+pub struct MongoBackend {
+    db: Database,
+    max_pool_size: u32,
 }
 
-impl MongoOperations for Database {
+impl MongoBackend {
+    pub fn new(db: Database, max_pool_size: u32) -> Self {
+        Self { db, max_pool_size }
+    }
+
     fn users_collection(&self) -> Collection<User> {
-        self.collection("users")
+        self.db.collection("users")
     }
-    async fn insert_user(&self, user: &User) -> Result<(), mongodb::error::Error> {
-        self.users_collection().insert_one(user).await?;
+}
+
+#[async_trait]
+impl StorageBackend for MongoBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn setup(&self) -> Result<(), Error> {
+        // Records are keyed by our own `id` field, so index it like a primary key
+        let index = IndexModel::builder()
+            .keys(doc! { "id": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.users_collection()
+            .create_index(index)
+            .await
+            .map_err(mongo_err)?;
         Ok(())
     }
-    async fn read_users(&self) -> Result<Vec<User>, mongodb::error::Error> {
-        let cursor = self.users_collection().find(doc! {}).await?;
-        let users: Vec<User> = cursor.try_collect().await?;
+
+    async fn ping(&self) -> Result<(), Error> {
+        self.db
+            .run_command(doc! { "ping": 1 })
+            .await
+            .map_err(mongo_err)?;
+        Ok(())
+    }
+
+    async fn insert_user(&self, user: SharedUser) -> Result<(), Error> {
+        self.users_collection()
+            .insert_one(&*user)
+            .await
+            .map_err(mongo_err)?;
+        Ok(())
+    }
+
+    async fn insert_users(&self, users: SharedUsers) -> Result<usize, Error> {
+        let result = self
+            .users_collection()
+            .insert_many(users.iter())
+            .await
+            .map_err(mongo_err)?;
+        Ok(result.inserted_ids.len())
+    }
+
+    async fn read_users(&self) -> Result<Vec<User>, Error> {
+        let cursor = self
+            .users_collection()
+            .find(doc! {})
+            .await
+            .map_err(mongo_err)?;
+        let users: Vec<User> = cursor.try_collect().await.map_err(mongo_err)?;
         Ok(users)
     }
 
-    async fn clear_users(&self) -> Result<(), mongodb::error::Error> {
-        self.users_collection().delete_many(doc! {}).await?;
+    async fn get_user(&self, id: &str) -> Result<Option<User>, Error> {
+        self.users_collection()
+            .find_one(doc! { "id": id })
+            .await
+            .map_err(mongo_err)
+    }
+
+    async fn scan_users(&self, start_id: &str, limit: usize) -> Result<Vec<User>, Error> {
+        let cursor = self
+            .users_collection()
+            .find(doc! { "id": { "$gte": start_id } })
+            .sort(doc! { "id": 1 })
+            .limit(limit as i64)
+            .await
+            .map_err(mongo_err)?;
+        let users: Vec<User> = cursor.try_collect().await.map_err(mongo_err)?;
+        Ok(users)
+    }
+
+    async fn update_user(&self, id: &str, update: &UserUpdate) -> Result<bool, Error> {
+        let result = self
+            .users_collection()
+            .update_one(
+                doc! { "id": id },
+                doc! { "$set": {
+                    "email": &update.email,
+                    "age": update.age,
+                    "active": update.active,
+                } },
+            )
+            .await
+            .map_err(mongo_err)?;
+        Ok(result.matched_count == 1)
+    }
+
+    async fn delete_user(&self, id: &str) -> Result<bool, Error> {
+        let result = self
+            .users_collection()
+            .delete_one(doc! { "id": id })
+            .await
+            .map_err(mongo_err)?;
+        Ok(result.deleted_count == 1)
+    }
+
+    async fn clear_users(&self) -> Result<Option<usize>, Error> {
+        let result = self
+            .users_collection()
+            .delete_many(doc! {})
+            .await
+            .map_err(mongo_err)?;
+        Ok(Some(result.deleted_count as usize))
+    }
+
+    async fn create_active_age_index(&self) -> Result<(), Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "active": 1, "age": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(ACTIVE_AGE_INDEX.to_string())
+                    .build(),
+            )
+            .build();
+        self.users_collection()
+            .create_index(index)
+            .await
+            .map_err(mongo_err)?;
         Ok(())
     }
+
+    async fn drop_active_age_index(&self) -> Result<(), Error> {
+        // Dropping a missing index is an error, so check first
+        let collection = self.users_collection();
+        let names = collection.list_index_names().await.map_err(mongo_err)?;
+        if names.iter().any(|name| name == ACTIVE_AGE_INDEX) {
+            collection
+                .drop_index(ACTIVE_AGE_INDEX)
+                .await
+                .map_err(mongo_err)?;
+        }
+        Ok(())
+    }
+
+    async fn find_active_by_age(&self, min_age: i32, max_age: i32) -> Result<Vec<User>, Error> {
+        let cursor = self
+            .users_collection()
+            .find(doc! { "active": true, "age": { "$gte": min_age, "$lte": max_age } })
+            .await
+            .map_err(mongo_err)?;
+        let users: Vec<User> = cursor.try_collect().await.map_err(mongo_err)?;
+        Ok(users)
+    }
+
+    fn connection_limit(&self) -> Option<usize> {
+        Some(self.max_pool_size as usize)
+    }
 }
@@ -1,46 +1,468 @@
-use sqlx::PgPool;
+use async_trait::async_trait;
+use sqlx::{PgPool, Postgres, QueryBuilder, postgres::PgPoolCopyExt, types::Json};
 
-use crate::store::user_struct::User;
+use crate::{
+    store::{
+        error::Error,
+        user_struct::{User, UserUpdate},
+    },
+    utils::db_operations::storage_backend::{CopyFormat, SharedUser, SharedUsers, StorageBackend},
+};
 
-pub trait PgOperations {
-    async fn read_users(&self) -> Result<Vec<User>, sqlx::Error>;
-    async fn insert_users(&self, user: &User) -> Result<(), sqlx::Error>;
-    async fn clear_users(&self) -> Result<(), sqlx::Error>;
+const NAME: &str = "PostgreSQL";
+
+// COPY data is streamed to the server in pieces of about this size
+const COPY_CHUNK_BYTES: usize = 1 << 20;
+// Signature, flags and header extension length of the binary COPY format
+const COPY_BINARY_HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+const COPY_BINARY_TRAILER: &[u8] = &(-1i16).to_be_bytes();
+// Version byte that starts a jsonb value in binary format
+const JSONB_VERSION: u8 = 1;
+
+// Payload columns added after the first release; created on older tables too
+const PAYLOAD_COLUMNS: [&str; 4] = [
+    "ALTER TABLE users ADD COLUMN IF NOT EXISTS padding TEXT",
+    "ALTER TABLE users ADD COLUMN IF NOT EXISTS profile JSONB",
+    "ALTER TABLE users ADD COLUMN IF NOT EXISTS tags JSONB",
+    "ALTER TABLE users ADD COLUMN IF NOT EXISTS blob BYTEA",
+];
+
+fn pg_err(err: sqlx::Error) -> Error {
+    Error::backend(NAME, err)
+}
+
+// Text COPY column: backslash, tab and line breaks must be escaped
+fn push_text_field(buf: &mut Vec<u8>, value: &str) {
+    for byte in value.bytes() {
+        match byte {
+            b'\\' => buf.extend_from_slice(b"\\\\"),
+            b'\t' => buf.extend_from_slice(b"\\t"),
+            b'\n' => buf.extend_from_slice(b"\\n"),
+            b'\r' => buf.extend_from_slice(b"\\r"),
+            _ => buf.push(byte),
+        }
+    }
+}
+
+// Nullable text COPY column: `\N` stands for NULL
+fn push_text_optional(buf: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => push_text_field(buf, value),
+        None => buf.extend_from_slice(b"\\N"),
+    }
+}
+
+// bytea in hex input form, `\x` followed by two digits per byte
+fn bytea_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("\\x");
+    for byte in bytes {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+fn push_text_row(buf: &mut Vec<u8>, user: &User) -> Result<(), Error> {
+    let profile = user
+        .profile
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+    let tags = user.tags.as_ref().map(serde_json::to_string).transpose()?;
+
+    push_text_field(buf, &user.id);
+    buf.push(b'\t');
+    push_text_field(buf, &user.name);
+    buf.push(b'\t');
+    push_text_field(buf, &user.email);
+    buf.push(b'\t');
+    buf.extend_from_slice(user.age.to_string().as_bytes());
+    buf.push(b'\t');
+    buf.push(if user.active { b't' } else { b'f' });
+    buf.push(b'\t');
+    push_text_optional(buf, user.padding.as_deref());
+    buf.push(b'\t');
+    push_text_optional(buf, profile.as_deref());
+    buf.push(b'\t');
+    push_text_optional(buf, tags.as_deref());
+    buf.push(b'\t');
+    push_text_optional(buf, user.blob.as_deref().map(bytea_hex).as_deref());
+    buf.push(b'\n');
+    Ok(())
+}
+
+// Binary COPY column: big-endian length, then the value in its send format
+fn push_binary_field(buf: &mut Vec<u8>, value: &[u8]) {
+    buf.extend_from_slice(&(value.len() as i32).to_be_bytes());
+    buf.extend_from_slice(value);
+}
+
+// Nullable binary COPY column: a length of -1 stands for NULL
+fn push_binary_optional(buf: &mut Vec<u8>, value: Option<&[u8]>) {
+    match value {
+        Some(value) => push_binary_field(buf, value),
+        None => buf.extend_from_slice(&(-1i32).to_be_bytes()),
+    }
+}
+
+fn jsonb_binary(value: &impl serde::Serialize) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![JSONB_VERSION];
+    serde_json::to_writer(&mut bytes, value)?;
+    Ok(bytes)
+}
+
+fn push_binary_row(buf: &mut Vec<u8>, user: &User) -> Result<(), Error> {
+    let profile = user.profile.as_ref().map(jsonb_binary).transpose()?;
+    let tags = user.tags.as_ref().map(jsonb_binary).transpose()?;
+
+    buf.extend_from_slice(&9i16.to_be_bytes());
+    push_binary_field(buf, user.id.as_bytes());
+    push_binary_field(buf, user.name.as_bytes());
+    push_binary_field(buf, user.email.as_bytes());
+    push_binary_field(buf, &user.age.to_be_bytes());
+    push_binary_field(buf, &[user.active as u8]);
+    push_binary_optional(buf, user.padding.as_deref().map(str::as_bytes));
+    push_binary_optional(buf, profile.as_deref());
+    push_binary_optional(buf, tags.as_deref());
+    push_binary_optional(buf, user.blob.as_deref());
+    Ok(())
+}
+
+pub struct PgBackend {
+    pool: PgPool,
+}
+
+impl PgBackend {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
 }
 
-impl PgOperations for PgPool {
-    async fn insert_users(&self, user: &User) -> Result<(), sqlx::Error> {
+#[async_trait]
+impl StorageBackend for PgBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn setup(&self) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS users (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                email TEXT NOT NULL,
+                age INT NOT NULL,
+                active BOOLEAN NOT NULL,
+                padding TEXT,
+                profile JSONB,
+                tags JSONB,
+                blob BYTEA
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(pg_err)?;
+
+        for statement in PAYLOAD_COLUMNS {
+            sqlx::query(statement)
+                .execute(&self.pool)
+                .await
+                .map_err(pg_err)?;
+        }
+
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<(), Error> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(pg_err)?;
+        Ok(())
+    }
+
+    async fn insert_user(&self, user: SharedUser) -> Result<(), Error> {
         sqlx::query(
             r#"
-            INSERT INTO users (id, name, email, age, active)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO users (id, name, email, age, active, padding, profile, tags, blob)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             "#,
         )
         .bind(&user.id)
         .bind(&user.name)
         .bind(&user.email)
-        .bind(&user.age)
-        .bind(&user.active)
-        .execute(self)
-        .await?;
+        .bind(user.age)
+        .bind(user.active)
+        .bind(&user.padding)
+        .bind(user.profile.as_ref().map(Json))
+        .bind(user.tags.as_ref().map(Json))
+        .bind(&user.blob)
+        .execute(&self.pool)
+        .await
+        .map_err(pg_err)?;
 
         Ok(())
     }
 
-    async fn read_users(&self) -> Result<Vec<User>, sqlx::Error> {
+    async fn insert_users(&self, users: SharedUsers) -> Result<usize, Error> {
+        // One multi-row INSERT; batch_size is capped so the binds stay under
+        // the protocol limit of 65535 parameters
+        let mut query = QueryBuilder::<Postgres>::new(
+            "INSERT INTO users (id, name, email, age, active, padding, profile, tags, blob) ",
+        );
+        query.push_values(users.iter(), |mut row, user| {
+            row.push_bind(&user.id)
+                .push_bind(&user.name)
+                .push_bind(&user.email)
+                .push_bind(user.age)
+                .push_bind(user.active)
+                .push_bind(&user.padding)
+                .push_bind(user.profile.as_ref().map(Json))
+                .push_bind(user.tags.as_ref().map(Json))
+                .push_bind(&user.blob);
+        });
+
+        let result = query.build().execute(&self.pool).await.map_err(pg_err)?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    // COPY streams in `COPY_CHUNK_BYTES` pieces, so the batch size is unused
+    async fn bulk_load(
+        &self,
+        users: SharedUsers,
+        format: CopyFormat,
+        _batch_size: usize,
+    ) -> Result<usize, Error> {
+        let statement = match format {
+            CopyFormat::Text => {
+                "COPY users (id, name, email, age, active, padding, profile, tags, blob) \
+                 FROM STDIN"
+            }
+            CopyFormat::Binary => {
+                "COPY users (id, name, email, age, active, padding, profile, tags, blob) \
+                 FROM STDIN WITH (FORMAT binary)"
+            }
+        };
+        let mut copy = self.pool.copy_in_raw(statement).await.map_err(pg_err)?;
+
+        let mut buf = Vec::with_capacity(COPY_CHUNK_BYTES);
+        if format == CopyFormat::Binary {
+            buf.extend_from_slice(COPY_BINARY_HEADER);
+        }
+        for user in users.iter() {
+            match format {
+                CopyFormat::Text => push_text_row(&mut buf, user)?,
+                CopyFormat::Binary => push_binary_row(&mut buf, user)?,
+            }
+            if buf.len() >= COPY_CHUNK_BYTES {
+                copy.send(std::mem::take(&mut buf)).await.map_err(pg_err)?;
+            }
+        }
+        if format == CopyFormat::Binary {
+            buf.extend_from_slice(COPY_BINARY_TRAILER);
+        }
+        copy.send(buf).await.map_err(pg_err)?;
+
+        let rows = copy.finish().await.map_err(pg_err)?;
+        Ok(rows as usize)
+    }
+
+    async fn read_users(&self) -> Result<Vec<User>, Error> {
         let users = sqlx::query_as::<_, User>(
             r#"
-            SELECT id, name, email, age, active
+            SELECT id, name, email, age, active, padding, profile, tags, blob
             FROM users
         "#,
         )
-        .fetch_all(self)
-        .await?;
+        .fetch_all(&self.pool)
+        .await
+        .map_err(pg_err)?;
+        Ok(users)
+    }
+
+    async fn get_user(&self, id: &str) -> Result<Option<User>, Error> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            SELECT id, name, email, age, active, padding, profile, tags, blob
+            FROM users
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(pg_err)?;
+        Ok(user)
+    }
+
+    async fn scan_users(&self, start_id: &str, limit: usize) -> Result<Vec<User>, Error> {
+        let users = sqlx::query_as::<_, User>(
+            r#"
+            SELECT id, name, email, age, active, padding, profile, tags, blob
+            FROM users
+            WHERE id >= $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(start_id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(pg_err)?;
         Ok(users)
     }
 
-    async fn clear_users(&self) -> Result<(), sqlx::Error> {
-        sqlx::query("TRUNCATE TABLE users").execute(self).await?;
+    async fn update_user(&self, id: &str, update: &UserUpdate) -> Result<bool, Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET email = $1, age = $2, active = $3
+            WHERE id = $4
+            "#,
+        )
+        .bind(&update.email)
+        .bind(update.age)
+        .bind(update.active)
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(pg_err)?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn delete_user(&self, id: &str) -> Result<bool, Error> {
+        let result = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(pg_err)?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn clear_users(&self) -> Result<Option<usize>, Error> {
+        // TRUNCATE reports no row count; `count_users` is taken beforehand
+        sqlx::query("TRUNCATE TABLE users")
+            .execute(&self.pool)
+            .await
+            .map_err(pg_err)?;
+        Ok(None)
+    }
+
+    async fn count_users(&self) -> Result<Option<usize>, Error> {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(pg_err)?;
+        Ok(Some(count as usize))
+    }
+
+    async fn create_active_age_index(&self) -> Result<(), Error> {
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS users_active_age_idx ON users USING btree (active, age)",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(pg_err)?;
+        Ok(())
+    }
+
+    async fn drop_active_age_index(&self) -> Result<(), Error> {
+        sqlx::query("DROP INDEX IF EXISTS users_active_age_idx")
+            .execute(&self.pool)
+            .await
+            .map_err(pg_err)?;
         Ok(())
     }
+
+    async fn find_active_by_age(&self, min_age: i32, max_age: i32) -> Result<Vec<User>, Error> {
+        let users = sqlx::query_as::<_, User>(
+            r#"
+            SELECT id, name, email, age, active, padding, profile, tags, blob
+            FROM users
+            WHERE age BETWEEN $1 AND $2 AND active
+            "#,
+        )
+        .bind(min_age)
+        .bind(max_age)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(pg_err)?;
+        Ok(users)
+    }
+
+    fn connection_limit(&self) -> Option<usize> {
+        Some(self.pool.options().get_max_connections() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user() -> User {
+        User {
+            id: "a\tb".to_string(),
+            name: "back\\slash".to_string(),
+            email: "line\nbreak\r".to_string(),
+            age: 30,
+            active: true,
+            padding: None,
+            profile: None,
+            tags: Some(vec!["x".to_string()]),
+            blob: Some(vec![0x00, 0xab, 0xff]),
+        }
+    }
+
+    #[test]
+    fn text_fields_escape_copy_delimiters() {
+        let mut buf = Vec::new();
+        push_text_field(&mut buf, "a\tb\nc\rd\\e\\N");
+        assert_eq!(buf, b"a\\tb\\nc\\rd\\\\e\\\\N");
+
+        assert_eq!(bytea_hex(&[0x00, 0xab, 0xff]), "\\x00abff");
+        assert_eq!(bytea_hex(&[]), "\\x");
+    }
+
+    #[test]
+    fn text_row_marks_nulls_and_ends_in_a_newline() {
+        let mut buf = Vec::new();
+        push_text_row(&mut buf, &user()).unwrap();
+        assert_eq!(
+            buf,
+            b"a\\tb\tback\\\\slash\tline\\nbreak\\r\t30\tt\t\\N\t\\N\t[\"x\"]\t\\\\x00abff\n"
+        );
+    }
+
+    #[test]
+    fn binary_row_is_a_field_count_then_length_prefixed_fields() {
+        let mut buf = Vec::new();
+        push_binary_row(&mut buf, &user()).unwrap();
+
+        let mut expected = 9i16.to_be_bytes().to_vec();
+        for field in [
+            &b"a\tb"[..],
+            b"back\\slash",
+            b"line\nbreak\r",
+            &30i32.to_be_bytes(),
+            &[1],
+        ] {
+            expected.extend_from_slice(&(field.len() as i32).to_be_bytes());
+            expected.extend_from_slice(field);
+        }
+        // padding and profile are NULL
+        expected.extend_from_slice(&(-1i32).to_be_bytes());
+        expected.extend_from_slice(&(-1i32).to_be_bytes());
+        expected.extend_from_slice(&6i32.to_be_bytes());
+        expected.extend_from_slice(b"\x01[\"x\"]");
+        expected.extend_from_slice(&3i32.to_be_bytes());
+        expected.extend_from_slice(&[0x00, 0xab, 0xff]);
+        assert_eq!(buf, expected);
+
+        assert_eq!(jsonb_binary(&["x"]).unwrap(), b"\x01[\"x\"]");
+        assert_eq!(&COPY_BINARY_HEADER[..11], b"PGCOPY\n\xff\r\n\0");
+        assert_eq!(COPY_BINARY_HEADER.len(), 19);
+        assert_eq!(COPY_BINARY_TRAILER, b"\xff\xff");
+    }
 }
@@ -1,6 +1,28 @@
-use rocksdb::DB as RocksDB;
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
 
-use crate::store::user_struct::User;
+use rocksdb::{DB as RocksDB, Direction, IteratorMode, WriteBatch};
+
+use crate::{
+    store::{
+        error::Error,
+        user_struct::{User, UserUpdate},
+    },
+    utils::db_operations::{
+        kv_keys::{
+            ACTIVE_AGE_PREFIX, ActiveAgeIndex, KvBatch, USER_PREFIX, active_age_in_range,
+            active_age_key, active_age_range, decode_user, encode_user, user_key,
+            user_key_for_index_entry,
+        },
+        storage_backend::BlockingBackend,
+    },
+};
+
+const NAME: &str = "RocksDB";
+// Locks shared out among record ids by hash
+const KEY_LOCKS: usize = 64;
 
 // Custom error type for RocksDB operations
 #[derive(Debug)]
@@ -24,59 +46,249 @@ impl std::fmt::Display for RocksError {
     }
 }
 
+impl std::error::Error for RocksError {}
+
+impl From<RocksError> for Error {
+    fn from(err: RocksError) -> Self {
+        Error::backend(NAME, err)
+    }
+}
+
 //RocksDB Operations
-pub trait RocksOperations {
-    fn insert_user(&self, user: &User) -> Result<(), RocksError>;
-    fn read_users(&self) -> Result<Vec<User>, RocksError>;
-    fn clear_users(&self) -> Result<(), RocksError>;
+pub struct RocksBackend {
+    db: RocksDB,
+    index: ActiveAgeIndex,
+    // Held across an update's or delete's read and write, so two of them on
+    // one record cannot both rewrite its index entry from the same old value
+    key_locks: [Mutex<()>; KEY_LOCKS],
 }
 
-impl RocksOperations for RocksDB {
-    fn insert_user(&self, user: &User) -> Result<(), RocksError> {
-        let key = format!("user:{}", user.id);
-        let value = match serde_json::to_vec(user) {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(RocksError::Serialization(e.to_string()));
-            }
+impl KvBatch for WriteBatch {
+    fn put(&mut self, key: Vec<u8>, value: &[u8]) {
+        WriteBatch::put(self, key, value);
+    }
+
+    fn delete(&mut self, key: Vec<u8>) {
+        WriteBatch::delete(self, key);
+    }
+}
+
+// Key and value as yielded by RocksDB iterators
+type Entry = (Box<[u8]>, Box<[u8]>);
+
+fn serialize(user: &User) -> Result<Vec<u8>, RocksError> {
+    encode_user(user).map_err(|e| RocksError::Serialization(e.to_string()))
+}
+
+fn deserialize(value: &[u8]) -> Result<User, RocksError> {
+    decode_user(value).map_err(|e| RocksError::Serialization(e.to_string()))
+}
+
+impl RocksBackend {
+    pub fn new(db: RocksDB) -> Self {
+        let first_key = match db.prefix_iterator(ACTIVE_AGE_PREFIX).next() {
+            Some(Ok((key, _))) => Some(key),
+            _ => None,
         };
-        self.put(key.as_bytes(), value)?;
+        let index = ActiveAgeIndex::detect(first_key.as_deref());
+        Self {
+            db,
+            index,
+            key_locks: std::array::from_fn(|_| Mutex::new(())),
+        }
+    }
+
+    fn lock_key(&self, id: &str) -> MutexGuard<'_, ()> {
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        self.key_locks[hasher.finish() as usize % KEY_LOCKS]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Keys and values under `prefix`; `prefix_iterator` runs past the prefix
+    // without a prefix extractor, so stop at the first foreign key
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<Entry>, RocksError> {
+        let mut entries = Vec::new();
+        for item in self.db.prefix_iterator(prefix) {
+            let (key, value) = item?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            entries.push((key, value));
+        }
+        Ok(entries)
+    }
+
+    // Delete every key under `prefix` in one batch, returning how many there were
+    fn delete_prefix(&self, prefix: &[u8]) -> Result<usize, RocksError> {
+        let entries = self.scan_prefix(prefix)?;
+        let deleted = entries.len();
+        let mut batch = WriteBatch::default();
+        for (key, _) in entries {
+            batch.delete(key);
+        }
+        self.db.write(batch)?;
+        Ok(deleted)
+    }
+}
+
+impl BlockingBackend for RocksBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn ping(&self) -> Result<(), Error> {
+        self.db.get(USER_PREFIX).map_err(RocksError::from)?;
         Ok(())
     }
 
-    fn read_users(&self) -> Result<Vec<User>, RocksError> {
+    fn insert_user(&self, user: &User) -> Result<(), Error> {
+        if !self.index.is_built() {
+            self.db
+                .put(user_key(&user.id), serialize(user)?)
+                .map_err(RocksError::from)?;
+            return Ok(());
+        }
+
+        let mut batch = WriteBatch::default();
+        self.index.put_user(&mut batch, user, &serialize(user)?);
+        self.db.write(batch).map_err(RocksError::from)?;
+        Ok(())
+    }
+
+    fn insert_users(&self, users: &[User]) -> Result<usize, Error> {
+        let mut batch = WriteBatch::default();
+        for user in users {
+            self.index.put_user(&mut batch, user, &serialize(user)?);
+        }
+
+        self.db.write(batch).map_err(RocksError::from)?;
+        Ok(users.len())
+    }
+
+    fn read_users(&self) -> Result<Vec<User>, Error> {
         let mut users = Vec::new();
-        let iter = self.prefix_iterator(b"user:");
+        for (_, value) in self.scan_prefix(USER_PREFIX)? {
+            users.push(deserialize(&value)?);
+        }
 
-        for item in iter {
-            let (_, value) = match item {
-                Ok(kv) => kv,
-                Err(e) => return Err(RocksError::RocksDb(e)),
-            };
+        Ok(users)
+    }
 
-            let user: User = match serde_json::from_slice(&value) {
-                Ok(u) => u,
-                Err(e) => {
-                    return Err(RocksError::Serialization(e.to_string()));
-                }
-            };
-            users.push(user);
+    fn get_user(&self, id: &str) -> Result<Option<User>, Error> {
+        match self.db.get(user_key(id)).map_err(RocksError::from)? {
+            Some(value) => Ok(Some(deserialize(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn scan_users(&self, start_id: &str, limit: usize) -> Result<Vec<User>, Error> {
+        let start = user_key(start_id);
+        let mut users = Vec::new();
+        for item in self
+            .db
+            .iterator(IteratorMode::From(&start, Direction::Forward))
+            .take(limit)
+        {
+            let (key, value) = item.map_err(RocksError::from)?;
+            if !key.starts_with(USER_PREFIX) {
+                break;
+            }
+            users.push(deserialize(&value)?);
         }
 
         Ok(users)
     }
 
-    fn clear_users(&self) -> Result<(), RocksError> {
-        let iter = self.prefix_iterator(b"user:");
+    fn update_user(&self, id: &str, update: &UserUpdate) -> Result<bool, Error> {
+        // No in-place update in a key-value store: read, modify, write back
+        let _guard = self.lock_key(id);
+        let mut user = match self.get_user(id)? {
+            Some(u) => u,
+            None => return Ok(false),
+        };
 
-        for item in iter {
-            let (key, _) = match item {
-                Ok(kv) => kv,
-                Err(e) => return Err(RocksError::RocksDb(e)),
-            };
-            self.delete(&key)?;
+        let mut batch = WriteBatch::default();
+        self.index.remove_entry(&mut batch, &user);
+        update.apply(&mut user);
+        self.index.put_user(&mut batch, &user, &serialize(&user)?);
+        self.db.write(batch).map_err(RocksError::from)?;
+        Ok(true)
+    }
+
+    fn delete_user(&self, id: &str) -> Result<bool, Error> {
+        let _guard = self.lock_key(id);
+        let user = match self.get_user(id)? {
+            Some(u) => u,
+            None => return Ok(false),
+        };
+
+        let mut batch = WriteBatch::default();
+        self.index.delete_user(&mut batch, &user);
+        self.db.write(batch).map_err(RocksError::from)?;
+        Ok(true)
+    }
+
+    fn clear_users(&self) -> Result<usize, Error> {
+        let cleared = self.delete_prefix(USER_PREFIX)?;
+        self.delete_prefix(ACTIVE_AGE_PREFIX)?;
+        Ok(cleared)
+    }
+
+    fn create_active_age_index(&self) -> Result<(), Error> {
+        let mut batch = WriteBatch::default();
+        for (_, value) in self.scan_prefix(USER_PREFIX)? {
+            batch.put(active_age_key(&deserialize(&value)?), b"");
         }
 
+        self.db.write(batch).map_err(RocksError::from)?;
+        self.index.set_built(true);
         Ok(())
     }
+
+    fn drop_active_age_index(&self) -> Result<(), Error> {
+        self.index.set_built(false);
+        self.delete_prefix(ACTIVE_AGE_PREFIX)?;
+        Ok(())
+    }
+
+    fn find_active_by_age(&self, min_age: i32, max_age: i32) -> Result<Vec<User>, Error> {
+        if !self.index.is_built() {
+            // No index: scan every record and filter
+            let users = self.read_users()?;
+            return Ok(users
+                .into_iter()
+                .filter(|u| u.active && (min_age..=max_age).contains(&u.age))
+                .collect());
+        }
+
+        let (start, end) = active_age_range(min_age, max_age);
+        let mut users = Vec::new();
+        for item in self
+            .db
+            .iterator(IteratorMode::From(&start, Direction::Forward))
+        {
+            let (key, _) = item.map_err(RocksError::from)?;
+            if !active_age_in_range(&key, &end) {
+                break;
+            }
+            let Some(value) = self
+                .db
+                .get(user_key_for_index_entry(&key))
+                .map_err(RocksError::from)?
+            else {
+                continue;
+            };
+            // The iterator is not a snapshot, so an entry may be stale by the
+            // time its record is read; keep the record only if it still has
+            // this entry, which also means it is active and in range
+            let user = deserialize(&value)?;
+            if *active_age_key(&user) == *key {
+                users.push(user);
+            }
+        }
+
+        Ok(users)
+    }
 }
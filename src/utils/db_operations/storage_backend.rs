@@ -0,0 +1,326 @@
+use std::{
+    ops::{Deref, Range},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use crate::store::{
+    error::Error,
+    user_struct::{User, UserUpdate},
+};
+
+// Wire format of a bulk load, for engines that distinguish them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyFormat {
+    Text,
+    Binary,
+}
+
+// Users handed to a backend as a range of a dataset shared with the caller,
+// so engines on the blocking pool can hold them past the call without a copy
+#[derive(Clone)]
+pub struct SharedUsers {
+    all: Arc<[User]>,
+    range: Range<usize>,
+}
+
+impl SharedUsers {
+    pub fn new(all: Arc<[User]>) -> Self {
+        let range = 0..all.len();
+        Self { all, range }
+    }
+
+    /// Consecutive runs of up to `size` users, sharing the same storage
+    pub fn batches(&self, size: usize) -> impl Iterator<Item = SharedUsers> + '_ {
+        let end = self.range.end;
+        self.range
+            .clone()
+            .step_by(size.max(1))
+            .map(move |start| SharedUsers {
+                all: Arc::clone(&self.all),
+                range: start..start.saturating_add(size).min(end),
+            })
+    }
+
+    /// Each user on its own, sharing the same storage
+    pub fn records(&self) -> impl Iterator<Item = SharedUser> + '_ {
+        self.range.clone().map(move |index| SharedUser {
+            all: Arc::clone(&self.all),
+            index,
+        })
+    }
+}
+
+impl Deref for SharedUsers {
+    type Target = [User];
+
+    fn deref(&self) -> &[User] {
+        &self.all[self.range.clone()]
+    }
+}
+
+// One user of a shared dataset
+#[derive(Clone)]
+pub struct SharedUser {
+    all: Arc<[User]>,
+    index: usize,
+}
+
+impl Deref for SharedUser {
+    type Target = User;
+
+    fn deref(&self) -> &User {
+        &self.all[self.index]
+    }
+}
+
+impl From<User> for SharedUser {
+    fn from(user: User) -> Self {
+        Self {
+            all: Arc::from([user]),
+            index: 0,
+        }
+    }
+}
+
+// Calls an adapter handed to the blocking thread pool, and the time they spent
+// outside the engine: waiting for a thread, the hand-off and the wake-up
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoundaryStats {
+    pub calls: u64,
+    pub overhead: Duration,
+}
+
+impl BoundaryStats {
+    /// Calls and overhead accumulated since `earlier`
+    pub fn since(&self, earlier: BoundaryStats) -> BoundaryStats {
+        BoundaryStats {
+            calls: self.calls - earlier.calls,
+            overhead: self.overhead.saturating_sub(earlier.overhead),
+        }
+    }
+}
+
+// Common interface every benchmarked database implements
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Display name used in benchmark results
+    fn name(&self) -> &'static str;
+
+    /// One-time schema setup (tables, collections), run at startup
+    async fn setup(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Cheap round trip used by the health check
+    async fn ping(&self) -> Result<(), Error>;
+
+    async fn insert_user(&self, user: SharedUser) -> Result<(), Error>;
+
+    /// Insert a batch of users in one round trip, returning how many were written
+    async fn insert_users(&self, users: SharedUsers) -> Result<usize, Error>;
+
+    /// Load a whole dataset through the engine's fastest ingestion path.
+    /// Defaults to batched inserts of `batch_size` records, so no single
+    /// request carries the whole dataset; PostgreSQL streams `COPY FROM STDIN`.
+    async fn bulk_load(
+        &self,
+        users: SharedUsers,
+        _format: CopyFormat,
+        batch_size: usize,
+    ) -> Result<usize, Error> {
+        let mut loaded = 0;
+        for batch in users.batches(batch_size) {
+            loaded += self.insert_users(batch).await?;
+        }
+        Ok(loaded)
+    }
+
+    async fn read_users(&self) -> Result<Vec<User>, Error>;
+
+    /// Fetch one user by primary key through the engine's native point read
+    async fn get_user(&self, id: &str) -> Result<Option<User>, Error>;
+
+    /// Up to `limit` users in primary-key order, starting at `start_id`
+    async fn scan_users(&self, start_id: &str, limit: usize) -> Result<Vec<User>, Error>;
+
+    /// Apply `update` to one user in place, returning whether the id existed
+    async fn update_user(&self, id: &str, update: &UserUpdate) -> Result<bool, Error>;
+
+    /// Delete one user by primary key, returning whether the id existed
+    async fn delete_user(&self, id: &str) -> Result<bool, Error>;
+
+    /// Remove every user, returning how many were removed when the engine
+    /// can tell; PostgreSQL's `TRUNCATE` cannot
+    async fn clear_users(&self) -> Result<Option<usize>, Error>;
+
+    /// Users stored, for engines whose clear cannot count what it removes;
+    /// the benchmark calls it untimed just before the clear
+    async fn count_users(&self) -> Result<Option<usize>, Error> {
+        Ok(None)
+    }
+
+    /// Build the secondary index on (active, age) over the stored users
+    async fn create_active_age_index(&self) -> Result<(), Error>;
+
+    /// Drop the (active, age) index if it exists
+    async fn drop_active_age_index(&self) -> Result<(), Error>;
+
+    /// Active users aged `min_age..=max_age`, through the index when it exists
+    async fn find_active_by_age(&self, min_age: i32, max_age: i32) -> Result<Vec<User>, Error>;
+
+    /// Connections the driver's pool allows; operations in flight beyond it
+    /// only wait for a connection. `None` when there is no such limit.
+    fn connection_limit(&self) -> Option<usize> {
+        None
+    }
+
+    /// Running totals of the async/blocking boundary, for engines driven
+    /// through a thread pool; `None` for natively async drivers
+    fn boundary_stats(&self) -> Option<BoundaryStats> {
+        None
+    }
+}
+
+// Synchronous counterpart for embedded engines, exposed through `Blocking`
+pub trait BlockingBackend: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+
+    fn setup(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn ping(&self) -> Result<(), Error>;
+
+    fn insert_user(&self, user: &User) -> Result<(), Error>;
+    fn insert_users(&self, users: &[User]) -> Result<usize, Error>;
+    fn read_users(&self) -> Result<Vec<User>, Error>;
+    fn get_user(&self, id: &str) -> Result<Option<User>, Error>;
+    fn scan_users(&self, start_id: &str, limit: usize) -> Result<Vec<User>, Error>;
+    fn update_user(&self, id: &str, update: &UserUpdate) -> Result<bool, Error>;
+    fn delete_user(&self, id: &str) -> Result<bool, Error>;
+    fn clear_users(&self) -> Result<usize, Error>;
+    fn create_active_age_index(&self) -> Result<(), Error>;
+    fn drop_active_age_index(&self) -> Result<(), Error>;
+    fn find_active_by_age(&self, min_age: i32, max_age: i32) -> Result<Vec<User>, Error>;
+}
+
+// Adapts a `BlockingBackend` to the async `StorageBackend` interface. Every
+// call runs on Tokio's blocking pool so disk I/O never stalls a runtime worker.
+pub struct Blocking<B: BlockingBackend> {
+    inner: Arc<B>,
+    calls: AtomicU64,
+    overhead_nanos: AtomicU64,
+}
+
+impl<B: BlockingBackend> Blocking<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            calls: AtomicU64::new(0),
+            overhead_nanos: AtomicU64::new(0),
+        }
+    }
+
+    // Run `call` on the blocking pool, adding the time not spent inside it to
+    // the boundary overhead
+    async fn run<T, F>(&self, call: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&B) -> Result<T, Error> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        let start = Instant::now();
+        let (result, busy) = tokio::task::spawn_blocking(move || {
+            let start = Instant::now();
+            let result = call(&inner);
+            (result, start.elapsed())
+        })
+        .await
+        .map_err(|e| Error::backend(self.inner.name(), e))?;
+
+        let overhead = start.elapsed().saturating_sub(busy);
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.overhead_nanos.fetch_add(
+            u64::try_from(overhead.as_nanos()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+        result
+    }
+}
+
+#[async_trait]
+impl<B: BlockingBackend> StorageBackend for Blocking<B> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn setup(&self) -> Result<(), Error> {
+        self.run(|db| db.setup()).await
+    }
+
+    async fn ping(&self) -> Result<(), Error> {
+        self.run(|db| db.ping()).await
+    }
+
+    async fn insert_user(&self, user: SharedUser) -> Result<(), Error> {
+        self.run(move |db| db.insert_user(&user)).await
+    }
+
+    async fn insert_users(&self, users: SharedUsers) -> Result<usize, Error> {
+        self.run(move |db| db.insert_users(&users)).await
+    }
+
+    async fn read_users(&self) -> Result<Vec<User>, Error> {
+        self.run(|db| db.read_users()).await
+    }
+
+    async fn get_user(&self, id: &str) -> Result<Option<User>, Error> {
+        let id = id.to_string();
+        self.run(move |db| db.get_user(&id)).await
+    }
+
+    async fn scan_users(&self, start_id: &str, limit: usize) -> Result<Vec<User>, Error> {
+        let start_id = start_id.to_string();
+        self.run(move |db| db.scan_users(&start_id, limit)).await
+    }
+
+    async fn update_user(&self, id: &str, update: &UserUpdate) -> Result<bool, Error> {
+        let (id, update) = (id.to_string(), update.clone());
+        self.run(move |db| db.update_user(&id, &update)).await
+    }
+
+    async fn delete_user(&self, id: &str) -> Result<bool, Error> {
+        let id = id.to_string();
+        self.run(move |db| db.delete_user(&id)).await
+    }
+
+    async fn clear_users(&self) -> Result<Option<usize>, Error> {
+        self.run(|db| db.clear_users()).await.map(Some)
+    }
+
+    async fn create_active_age_index(&self) -> Result<(), Error> {
+        self.run(|db| db.create_active_age_index()).await
+    }
+
+    async fn drop_active_age_index(&self) -> Result<(), Error> {
+        self.run(|db| db.drop_active_age_index()).await
+    }
+
+    async fn find_active_by_age(&self, min_age: i32, max_age: i32) -> Result<Vec<User>, Error> {
+        self.run(move |db| db.find_active_by_age(min_age, max_age))
+            .await
+    }
+
+    fn boundary_stats(&self) -> Option<BoundaryStats> {
+        Some(BoundaryStats {
+            calls: self.calls.load(Ordering::Relaxed),
+            overhead: Duration::from_nanos(self.overhead_nanos.load(Ordering::Relaxed)),
+        })
+    }
+}
@@ -1,7 +1,20 @@
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use surrealdb::{Surreal, engine::remote::ws::Client, sql::Thing};
 
-use crate::store::user_struct::User;
+use crate::{
+    store::{
+        error::Error,
+        user_struct::{Profile, User, UserUpdate},
+    },
+    utils::db_operations::storage_backend::{SharedUser, SharedUsers, StorageBackend},
+};
+
+const NAME: &str = "SurrealDB";
+
+fn surreal_err(err: surrealdb::Error) -> Error {
+    Error::backend(NAME, err)
+}
 
 // SurrealDB specific user struct (handles Thing ID type)
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -11,49 +24,168 @@ pub struct SurrealUser {
     pub email: String,
     pub age: i32,
     pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub padding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "serde_bytes")]
+    pub blob: Option<Vec<u8>>,
+}
+
+impl From<SurrealUser> for User {
+    fn from(su: SurrealUser) -> Self {
+        let id = match su.id.id {
+            surrealdb::sql::Id::String(s) => s,
+            surrealdb::sql::Id::Number(n) => n.to_string(),
+            _ => su.id.id.to_string(),
+        };
+
+        User {
+            id,
+            name: su.name,
+            email: su.email,
+            age: su.age,
+            active: su.active,
+            padding: su.padding,
+            profile: su.profile,
+            tags: su.tags,
+            blob: su.blob,
+        }
+    }
 }
 
 //SurrealDB Operations
-pub trait SurrealOperations {
-    async fn insert_user(&self, user: &User) -> Result<(), surrealdb::Error>;
-    async fn read_users(&self) -> Result<Vec<User>, surrealdb::Error>;
-    async fn clear_users(&self) -> Result<(), surrealdb::Error>;
+pub struct SurrealBackend {
+    db: Surreal<Client>,
+}
+
+impl SurrealBackend {
+    pub fn new(db: Surreal<Client>) -> Self {
+        Self { db }
+    }
 }
 
-impl SurrealOperations for Surreal<Client> {
-    async fn insert_user(&self, user: &User) -> Result<(), surrealdb::Error> {
+#[async_trait]
+impl StorageBackend for SurrealBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn ping(&self) -> Result<(), Error> {
+        self.db.health().await.map_err(surreal_err)?;
+        Ok(())
+    }
+
+    async fn insert_user(&self, user: SharedUser) -> Result<(), Error> {
         let _: Option<SurrealUser> = self
+            .db
             .create(("users", user.id.clone()))
-            .content(user.clone())
-            .await?;
+            .content(User::clone(&user))
+            .await
+            .map_err(surreal_err)?;
         Ok(())
     }
 
-    async fn read_users(&self) -> Result<Vec<User>, surrealdb::Error> {
-        let surreal_users: Vec<SurrealUser> = self.select("users").await?;
-
-        let mut users = Vec::new();
-        for su in surreal_users {
-            let id_string = match su.id.id {
-                surrealdb::sql::Id::String(s) => s,
-                surrealdb::sql::Id::Number(n) => n.to_string(),
-                _ => su.id.id.to_string(),
-            };
-
-            users.push(User {
-                id: id_string,
-                name: su.name,
-                email: su.email,
-                age: su.age,
-                active: su.active,
-            });
-        }
+    async fn insert_users(&self, users: SharedUsers) -> Result<usize, Error> {
+        // Bulk INSERT into the table, keeping the same record ids `create` uses
+        let records: Vec<SurrealUser> = users
+            .iter()
+            .map(|user| SurrealUser {
+                id: Thing::from(("users", user.id.as_str())),
+                name: user.name.clone(),
+                email: user.email.clone(),
+                age: user.age,
+                active: user.active,
+                padding: user.padding.clone(),
+                profile: user.profile.clone(),
+                tags: user.tags.clone(),
+                blob: user.blob.clone(),
+            })
+            .collect();
 
-        Ok(users)
+        let inserted: Vec<SurrealUser> = self
+            .db
+            .insert("users")
+            .content(records)
+            .await
+            .map_err(surreal_err)?;
+        Ok(inserted.len())
     }
 
-    async fn clear_users(&self) -> Result<(), surrealdb::Error> {
-        let _: Vec<SurrealUser> = self.delete("users").await?;
+    async fn read_users(&self) -> Result<Vec<User>, Error> {
+        let surreal_users: Vec<SurrealUser> = self.db.select("users").await.map_err(surreal_err)?;
+
+        Ok(surreal_users.into_iter().map(User::from).collect())
+    }
+
+    async fn get_user(&self, id: &str) -> Result<Option<User>, Error> {
+        let user: Option<SurrealUser> = self.db.select(("users", id)).await.map_err(surreal_err)?;
+        Ok(user.map(User::from))
+    }
+
+    async fn scan_users(&self, start_id: &str, limit: usize) -> Result<Vec<User>, Error> {
+        let mut response = self
+            .db
+            .query("SELECT * FROM users WHERE id >= $start ORDER BY id LIMIT $limit")
+            .bind(("start", Thing::from(("users", start_id))))
+            .bind(("limit", limit))
+            .await
+            .map_err(surreal_err)?;
+        let surreal_users: Vec<SurrealUser> = response.take(0).map_err(surreal_err)?;
+        Ok(surreal_users.into_iter().map(User::from).collect())
+    }
+
+    async fn update_user(&self, id: &str, update: &UserUpdate) -> Result<bool, Error> {
+        let user: Option<SurrealUser> = self
+            .db
+            .update(("users", id))
+            .merge(update.clone())
+            .await
+            .map_err(surreal_err)?;
+        Ok(user.is_some())
+    }
+
+    async fn delete_user(&self, id: &str) -> Result<bool, Error> {
+        let user: Option<SurrealUser> = self.db.delete(("users", id)).await.map_err(surreal_err)?;
+        Ok(user.is_some())
+    }
+
+    async fn clear_users(&self) -> Result<Option<usize>, Error> {
+        let deleted: Vec<SurrealUser> = self.db.delete("users").await.map_err(surreal_err)?;
+        Ok(Some(deleted.len()))
+    }
+
+    async fn create_active_age_index(&self) -> Result<(), Error> {
+        self.db
+            .query("DEFINE INDEX IF NOT EXISTS users_active_age ON TABLE users FIELDS active, age")
+            .await
+            .map_err(surreal_err)?
+            .check()
+            .map_err(surreal_err)?;
+        Ok(())
+    }
+
+    async fn drop_active_age_index(&self) -> Result<(), Error> {
+        self.db
+            .query("REMOVE INDEX IF EXISTS users_active_age ON TABLE users")
+            .await
+            .map_err(surreal_err)?
+            .check()
+            .map_err(surreal_err)?;
         Ok(())
     }
+
+    async fn find_active_by_age(&self, min_age: i32, max_age: i32) -> Result<Vec<User>, Error> {
+        let mut response = self
+            .db
+            .query("SELECT * FROM users WHERE active = true AND age >= $min AND age <= $max")
+            .bind(("min", min_age))
+            .bind(("max", max_age))
+            .await
+            .map_err(surreal_err)?;
+        let surreal_users: Vec<SurrealUser> = response.take(0).map_err(surreal_err)?;
+        Ok(surreal_users.into_iter().map(User::from).collect())
+    }
 }